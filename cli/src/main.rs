@@ -0,0 +1,536 @@
+//! Command-line tool for operating red packets against a cluster (a local validator by default).
+
+mod output;
+
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::Discriminator;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use redpacket_client::{
+    claim_red_packet, claim_red_packet_with_secp256k1_signature, claim_red_packet_with_signature,
+    claim_red_packet_with_signatures, close_random_commit, commit_password_claim, commit_random_claim,
+    create_red_packet_with_id, decode_claim_receipt, decode_legacy_red_packet, decode_random_commit, decode_red_packet,
+    random_commit_address, red_packet_address_with_id, redpacket::{legacy, RANDOM_CLAIM_WINDOW_SLOTS},
+    update_claim_signer, withdraw_legacy_red_packet, withdraw_red_packet, Asset, AssetKind, ClaimAuthorization,
+    ClaimProof, ClaimReceipt, ClaimSignature, ClaimSignatures, ClaimTerms, CreateParams, RandomSplit, RandomnessSource,
+    RedPacket, Secp256k1ClaimSignature, SplitModeInput, PROGRAM_ID,
+};
+use redpacket_signer::{ClaimRequest, ClaimSignatureService, SignedClaimResponse};
+use solana_account_decoder::UiAccountEncoding;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature},
+    signer::Signer,
+    slot_hashes::MAX_ENTRIES,
+    transaction::Transaction,
+};
+
+#[derive(Parser)]
+#[command(name = "redpacket", about = "Create, inspect, claim and withdraw red packets")]
+struct Cli {
+    /// RPC URL of the cluster
+    #[arg(long, global = true, default_value = "http://127.0.0.1:8899")]
+    url: String,
+    /// Keypair paying for and signing the transaction
+    #[arg(long, global = true)]
+    keypair: Option<PathBuf>,
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Split {
+    Equal,
+    Random,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ClaimMessage {
+    /// red_packet || claimer || recipient
+    Legacy,
+    /// Bound to the program id, with a deadline and an optional max amount
+    V1,
+}
+
+// How long claim signatures made locally by `claim --claim-signer-keypair` stay valid
+const LOCAL_SIGNATURE_VALIDITY: u64 = 5 * 60;
+
+// One group of flags per ClaimAuthorization kind, see claim_instructions
+#[derive(Args)]
+struct ClaimProofArgs {
+    /// Claim signature (base58) from the signing service (Signature and SignatureV1)
+    #[arg(long, conflicts_with = "claim_signer_keypair")]
+    signature: Option<String>,
+    /// Sign the claim locally with the claim signer keypair (Signature and SignatureV1)
+    #[arg(long)]
+    claim_signer_keypair: Option<PathBuf>,
+    /// A claim signer's signature as <pubkey>:<signature> in base58, repeated for each signer (MultiSignature)
+    #[arg(long = "signer-signature", value_name = "PUBKEY:SIGNATURE")]
+    signer_signatures: Vec<String>,
+    /// 65-byte r || s || v signature in hex from the EVM eligibility backend (Secp256k1)
+    #[arg(long)]
+    eth_signature: Option<String>,
+    /// --eth-signature was made with personal_sign, over the EIP-191 prefixed claim message
+    #[arg(long, requires = "eth_signature")]
+    personal_sign: bool,
+    /// Deadline covered by the signature(s), for v1 claim messages (SignatureV1, MultiSignature, Secp256k1)
+    #[arg(long)]
+    deadline: Option<u64>,
+    /// Max amount covered by the signature(s), for v1 claim messages
+    #[arg(long, default_value_t = 0, requires = "deadline")]
+    max_amount: u64,
+    /// Merkle proof node in hex, repeated from the leaf up (MerkleAllowlist)
+    #[arg(long = "proof", value_name = "NODE")]
+    proof: Vec<String>,
+    /// Amount in the claimer's allowlist leaf, 0 for the regular split (MerkleAllowlist)
+    #[arg(long, default_value_t = 0)]
+    allowlist_amount: u64,
+    /// Phrase, committed to in a first transaction and revealed by the claim (Password)
+    #[arg(long)]
+    password: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a red packet addressed by a random id, funded by --keypair
+    Create {
+        /// SPL or Token-2022 mint, native SOL when omitted
+        #[arg(long)]
+        mint: Option<Pubkey>,
+        #[arg(long)]
+        total_number: u32,
+        /// In lamports or base token units
+        #[arg(long)]
+        total_amount: u64,
+        /// Seconds until the red packet expires
+        #[arg(long, default_value_t = 24 * 60 * 60)]
+        duration: u64,
+        /// Unix time at which claims open, right away when omitted
+        #[arg(long, default_value_t = 0)]
+        open_time: u64,
+        #[arg(long, value_enum, default_value_t = Split::Equal)]
+        split: Split,
+        /// Key issuing claim signatures, --keypair's key when omitted
+        #[arg(long)]
+        claim_signer: Option<Pubkey>,
+        /// Message format the claim signatures have to sign
+        #[arg(long, value_enum, default_value_t = ClaimMessage::Legacy)]
+        claim_message: ClaimMessage,
+        #[arg(long, default_value = "")]
+        name: String,
+        #[arg(long, default_value = "")]
+        message: String,
+        /// Record claims in claim receipt accounts, allowing more than 200 claimers
+        #[arg(long)]
+        claim_receipts: bool,
+    },
+    /// Print a red packet's fields and claim records
+    Show { red_packet: Pubkey },
+    /// Print who claimed how much, from the red packet or its claim receipts
+    ListClaims { red_packet: Pubkey },
+    /// Claim as --keypair, with the proof the red packet's claim authorization takes
+    Claim {
+        red_packet: Pubkey,
+        /// Account receiving the claim, --keypair's key when omitted
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+        #[command(flatten)]
+        proof: ClaimProofArgs,
+        /// Keep a fully claimed red packet open instead of closing it with the last claim
+        #[arg(long)]
+        keep_open: bool,
+    },
+    /// Withdraw the unclaimed remainder and close the red packet as its creator (--keypair)
+    Withdraw { red_packet: Pubkey },
+    /// Rotate the claim signing key, or pause claims, as the creator (--keypair)
+    SetClaimSigner {
+        red_packet: Pubkey,
+        /// New pubkey_for_claim_signature, also resuming paused claims
+        #[arg(long, required_unless_present = "pause", conflicts_with = "pause")]
+        signer: Option<Pubkey>,
+        #[arg(long)]
+        pause: bool,
+    },
+    /// Sign a claim with the claim signer keypair (--keypair) without sending anything
+    SignClaim {
+        red_packet: Pubkey,
+        #[arg(long)]
+        claimer: Pubkey,
+        /// Account receiving the claim, the claimer when omitted
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+        /// Sign a v1 claim message valid for this many seconds instead of a legacy one
+        #[arg(long)]
+        valid_for: Option<u64>,
+        /// Max claim amount covered by the v1 claim message, 0 for no cap
+        #[arg(long, default_value_t = 0, requires = "valid_for")]
+        max_amount: u64,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let rpc = RpcClient::new_with_commitment(cli.url.clone(), CommitmentConfig::confirmed());
+
+    match cli.command {
+        Command::Create {
+            mint,
+            total_number,
+            total_amount,
+            duration,
+            open_time,
+            split,
+            claim_signer,
+            claim_message,
+            name,
+            message,
+            claim_receipts,
+        } => {
+            let payer = load_keypair(cli.keypair.as_ref())?;
+            let asset = match mint {
+                Some(mint) => Asset::SplToken { mint, token_program: rpc.get_account(&mint)?.owner },
+                None => Asset::Native,
+            };
+            let split_mode = match split {
+                Split::Equal => SplitModeInput::Equal,
+                Split::Random => SplitModeInput::Random { split: RandomSplit::Legacy },
+            };
+            let params = CreateParams {
+                total_number,
+                total_amount,
+                duration,
+                open_time,
+                split_mode,
+                pubkey_for_claim_signature: claim_signer.unwrap_or(payer.pubkey()),
+                name,
+                message,
+                use_claim_receipts: claim_receipts,
+                claim_authorization: match claim_message {
+                    ClaimMessage::Legacy => ClaimAuthorization::Signature,
+                    ClaimMessage::V1 => ClaimAuthorization::SignatureV1,
+                },
+                randomness: RandomnessSource::Timestamp,
+            };
+            let id = Keypair::new().pubkey().to_bytes();
+            let address = red_packet_address_with_id(&payer.pubkey(), &id).0;
+            let signature = send(&rpc, &payer, &[create_red_packet_with_id(&payer.pubkey(), id, &asset, params)])?;
+            output::print_transaction(cli.output, "red_packet", &address, &signature);
+        }
+        Command::Show { red_packet } => {
+            let account = fetch_red_packet(&rpc, &red_packet)?;
+            output::print_red_packet(cli.output, &red_packet, &account);
+        }
+        Command::ListClaims { red_packet } => {
+            let account = fetch_red_packet(&rpc, &red_packet)?;
+            let claims = if account.use_claim_receipts {
+                fetch_claim_receipts(&rpc, &red_packet, &account)?
+            } else {
+                output::claims_from_records(&account)
+            };
+            output::print_claims(cli.output, &claims);
+        }
+        Command::Claim { red_packet, recipient, proof, keep_open } => {
+            let claimer = load_keypair(cli.keypair.as_ref())?;
+            let recipient = recipient.unwrap_or(claimer.pubkey());
+            let account = fetch_red_packet(&rpc, &red_packet)?;
+            let asset = asset_of(&rpc, &account)?;
+            let instructions =
+                claim_instructions(&red_packet, &account, &asset, &claimer.pubkey(), &recipient, &proof, !keep_open)?;
+            if let Some(phrase) = &proof.password {
+                // the phrase is only revealed by the claim, in a later slot than the confirmed commitment
+                send(&rpc, &claimer, &[commit_password_claim(&red_packet, &claimer.pubkey(), phrase)])?;
+            }
+            // the share is drawn from the hash of the commit slot, so the commit lands first and
+            // the claim right after it
+            if account.needs_random_commit() {
+                commit_random_claim_now(&rpc, &claimer, &red_packet)?;
+            }
+            let signature = send(&rpc, &claimer, &instructions)?;
+            output::print_transaction(cli.output, "red_packet", &red_packet, &signature);
+        }
+        Command::Withdraw { red_packet } => {
+            let creator = load_keypair(cli.keypair.as_ref())?;
+            let data = rpc.get_account(&red_packet).with_context(|| format!("red packet {red_packet} not found"))?.data;
+            // packets from the first program version have their own layout and withdraw instruction
+            let instruction = match decode_legacy_red_packet(&data) {
+                Ok(legacy) => {
+                    if legacy.creator != creator.pubkey() {
+                        bail!("only the creator {} can withdraw this red packet", legacy.creator);
+                    }
+                    let asset = match legacy.token_type {
+                        legacy::TOKEN_TYPE_SPL => {
+                            let token_program = rpc.get_account(&legacy.token_address)?.owner;
+                            Asset::SplToken { mint: legacy.token_address, token_program }
+                        }
+                        _ => Asset::Native,
+                    };
+                    withdraw_legacy_red_packet(&red_packet, &legacy, &asset)
+                }
+                Err(_) => {
+                    let account = decode_red_packet(&data).map_err(|err| anyhow!("{red_packet} is not a red packet: {err}"))?;
+                    if account.creator != creator.pubkey() {
+                        bail!("only the creator {} can withdraw this red packet", account.creator);
+                    }
+                    withdraw_red_packet(&red_packet, &account, &asset_of(&rpc, &account)?)
+                }
+            };
+            let signature = send(&rpc, &creator, &[instruction])?;
+            output::print_transaction(cli.output, "red_packet", &red_packet, &signature);
+        }
+        Command::SetClaimSigner { red_packet, signer, pause: _ } => {
+            let creator = load_keypair(cli.keypair.as_ref())?;
+            let signature = send(&rpc, &creator, &[update_claim_signer(&red_packet, &creator.pubkey(), signer)])?;
+            output::print_transaction(cli.output, "red_packet", &red_packet, &signature);
+        }
+        Command::SignClaim { red_packet, claimer, recipient, valid_for, max_amount } => {
+            let request = ClaimRequest { red_packet, claimer, recipient: recipient.unwrap_or(claimer) };
+            let mut service = ClaimSignatureService::new(load_keypair(cli.keypair.as_ref())?);
+            if let Some(validity_secs) = valid_for {
+                service = service.with_claim_message_v1(validity_secs, max_amount);
+            }
+            let claim = service.issue(&request, unix_now())?;
+            output::print_signed_claim(cli.output, &SignedClaimResponse::from(&claim));
+        }
+    }
+    Ok(())
+}
+
+// The claim, after the verify instruction for signature kinds, checking that the flags given match
+// the red packet's claim authorization
+fn claim_instructions(
+    red_packet: &Pubkey,
+    account: &RedPacket,
+    asset: &Asset,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    args: &ClaimProofArgs,
+    close_when_emptied: bool,
+) -> Result<Vec<Instruction>> {
+    let authorization = &account.claim_authorization;
+    let multi_signature = matches!(authorization, ClaimAuthorization::MultiSignature { .. });
+    let secp256k1 = matches!(authorization, ClaimAuthorization::Secp256k1 { .. });
+    let flags = [
+        (
+            "--signature and --claim-signer-keypair",
+            args.signature.is_some() || args.claim_signer_keypair.is_some(),
+            authorization.uses_claim_signer(),
+        ),
+        ("--signer-signature", !args.signer_signatures.is_empty(), multi_signature),
+        ("--eth-signature", args.eth_signature.is_some(), secp256k1),
+        (
+            "--deadline",
+            args.deadline.is_some(),
+            *authorization == ClaimAuthorization::SignatureV1 || multi_signature || secp256k1,
+        ),
+        (
+            "--proof and --allowlist-amount",
+            !args.proof.is_empty() || args.allowlist_amount > 0,
+            matches!(authorization, ClaimAuthorization::MerkleAllowlist { .. }),
+        ),
+        ("--password", args.password.is_some(), matches!(authorization, ClaimAuthorization::Password { .. })),
+    ];
+    for (flag, given, applies) in flags {
+        if given && !applies {
+            let label = output::claim_authorization_label(account);
+            bail!("this red packet is claimed with {label}, which does not take {flag}");
+        }
+    }
+    let terms = args.deadline.map(|deadline| ClaimTerms { deadline, max_amount: args.max_amount });
+
+    Ok(match authorization {
+        ClaimAuthorization::Signature | ClaimAuthorization::SignatureV1 => {
+            let v1 = *authorization == ClaimAuthorization::SignatureV1;
+            let claim_signature = match (&args.signature, &args.claim_signer_keypair) {
+                (Some(signature), _) => {
+                    if v1 && terms.is_none() {
+                        bail!("this red packet takes v1 claim messages, pass the signed --deadline");
+                    }
+                    ClaimSignature { signature: Signature::from_str(signature)?.into(), terms }
+                }
+                (None, Some(path)) => {
+                    let request = ClaimRequest { red_packet: *red_packet, claimer: *claimer, recipient: *recipient };
+                    let mut service = ClaimSignatureService::new(load_keypair(Some(path))?);
+                    if v1 {
+                        service = service.with_claim_message_v1(LOCAL_SIGNATURE_VALIDITY, 0);
+                    }
+                    service.issue(&request, unix_now())?.claim_signature()
+                }
+                (None, None) => bail!("pass --signature or --claim-signer-keypair"),
+            };
+            claim_red_packet_with_signature(red_packet, account, asset, claimer, recipient, &claim_signature, close_when_emptied)
+                .to_vec()
+        }
+        ClaimAuthorization::MultiSignature { threshold, .. } => {
+            if args.signer_signatures.len() < *threshold as usize {
+                bail!("this red packet takes {threshold} claim signer signatures, pass each with --signer-signature");
+            }
+            let signatures = args.signer_signatures.iter().map(|pair| parse_signer_signature(pair)).collect::<Result<_>>()?;
+            let signatures = ClaimSignatures { signatures, terms };
+            claim_red_packet_with_signatures(red_packet, account, asset, claimer, recipient, &signatures, close_when_emptied)
+                .to_vec()
+        }
+        ClaimAuthorization::Secp256k1 { eth_address } => {
+            let eth_signature = args.eth_signature.as_deref().ok_or(anyhow!("pass the claim's --eth-signature"))?;
+            let eth_signature: [u8; 65] = parse_hex(eth_signature).context("--eth-signature")?;
+            let signature = Secp256k1ClaimSignature {
+                eth_address: *eth_address,
+                signature: eth_signature[..64].try_into().unwrap(),
+                // wallets add 27 to the recovery id
+                recovery_id: if eth_signature[64] >= 27 { eth_signature[64] - 27 } else { eth_signature[64] },
+                terms,
+                personal_sign: args.personal_sign,
+            };
+            // the verify instruction is the first one of the claim transaction
+            claim_red_packet_with_secp256k1_signature(
+                red_packet,
+                account,
+                asset,
+                claimer,
+                recipient,
+                &signature,
+                0,
+                close_when_emptied,
+            )
+            .to_vec()
+        }
+        ClaimAuthorization::MerkleAllowlist { .. } => {
+            let proof = args.proof.iter().map(|node| parse_hex(node).context("--proof")).collect::<Result<_>>()?;
+            let proof = ClaimProof::Merkle { proof, amount: args.allowlist_amount };
+            vec![claim_red_packet(red_packet, account, asset, claimer, recipient, proof, close_when_emptied)]
+        }
+        ClaimAuthorization::Password { .. } => {
+            let phrase = args.password.clone().ok_or(anyhow!("pass the red packet's --password"))?;
+            let proof = ClaimProof::Password { phrase };
+            vec![claim_red_packet(red_packet, account, asset, claimer, recipient, proof, close_when_emptied)]
+        }
+    })
+}
+
+// <pubkey>:<signature>, both base58
+fn parse_signer_signature(pair: &str) -> Result<(Pubkey, [u8; 64])> {
+    let (signer, signature) = pair.split_once(':').ok_or(anyhow!("--signer-signature {pair} is not <pubkey>:<signature>"))?;
+    Ok((Pubkey::from_str(signer)?, Signature::from_str(signature)?.into()))
+}
+
+fn parse_hex<const N: usize>(text: &str) -> Result<[u8; N]> {
+    let text = text.trim_start_matches("0x");
+    if text.len() != 2 * N {
+        bail!("expected {N} bytes in hex, got {text}");
+    }
+    let mut bytes = [0; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[2 * index..2 * index + 2], 16).with_context(|| format!("{text} is not hex"))?;
+    }
+    Ok(bytes)
+}
+
+fn load_keypair(path: Option<&PathBuf>) -> Result<Keypair> {
+    let path = match path {
+        Some(path) => path.clone(),
+        None => PathBuf::from(std::env::var("HOME")?).join(".config/solana/id.json"),
+    };
+    read_keypair_file(&path).map_err(|err| anyhow!("failed to read keypair {}: {err}", path.display()))
+}
+
+fn fetch_red_packet(rpc: &RpcClient, address: &Pubkey) -> Result<RedPacket> {
+    let account = rpc.get_account(address).with_context(|| format!("red packet {address} not found"))?;
+    decode_red_packet(&account.data).map_err(|err| anyhow!("{address} is not a red packet: {err}"))
+}
+
+// Every ClaimReceipt account whose red_packet and red_packet_create_slot fields (right after the
+// discriminator) match, leaving out receipts of earlier red packets at the same address
+fn fetch_claim_receipts(rpc: &RpcClient, red_packet: &Pubkey, account: &RedPacket) -> Result<Vec<output::Claim>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &ClaimReceipt::DISCRIMINATOR)),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(8, red_packet.as_ref())),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(40, &account.create_slot.to_le_bytes())),
+        ]),
+        account_config: RpcAccountInfoConfig { encoding: Some(UiAccountEncoding::Base64), ..Default::default() },
+        ..Default::default()
+    };
+    let mut receipts = rpc
+        .get_program_accounts_with_config(&PROGRAM_ID, config)?
+        .into_iter()
+        .map(|(_, account)| decode_claim_receipt(&account.data).map_err(|err| anyhow!("{err}")))
+        .collect::<Result<Vec<_>>>()?;
+    receipts.sort_by_key(|receipt| receipt.claim_time);
+    Ok(receipts
+        .into_iter()
+        .map(|receipt| output::Claim {
+            claimer: receipt.claimer,
+            recipient: receipt.recipient,
+            amount: receipt.amount,
+            claim_time: Some(receipt.claim_time),
+        })
+        .collect())
+}
+
+// The token program is the owner of the mint account
+fn asset_of(rpc: &RpcClient, red_packet: &RedPacket) -> Result<Asset> {
+    let token_program = match red_packet.token_type {
+        AssetKind::Native => Pubkey::default(),
+        AssetKind::SplToken => rpc.get_account(&red_packet.token_address)?.owner,
+    };
+    Ok(Asset::of(red_packet, token_program))
+}
+
+// Leaves a random commit the claim can follow within RANDOM_CLAIM_WINDOW_SLOTS, replacing one
+// from an earlier attempt that missed its window once close_random_commit allows it
+fn commit_random_claim_now(rpc: &RpcClient, claimer: &Keypair, red_packet: &Pubkey) -> Result<()> {
+    let mut instructions = vec![];
+    if let Ok(account) = rpc.get_account(&random_commit_address(red_packet, &claimer.pubkey()).0) {
+        let commit = decode_random_commit(&account.data).map_err(|err| anyhow!("{err}"))?;
+        let slot = rpc.get_slot()?;
+        if slot <= commit.slot + RANDOM_CLAIM_WINDOW_SLOTS {
+            return Ok(());
+        }
+        let reopens_after = commit.slot + MAX_ENTRIES as u64;
+        if slot <= reopens_after {
+            bail!("the random commit from slot {} missed its claim window, it can be made again after slot {reopens_after} (now {slot})", commit.slot);
+        }
+        instructions.push(close_random_commit(red_packet, &claimer.pubkey()));
+    }
+    instructions.push(commit_random_claim(red_packet, &claimer.pubkey()));
+    // the claim only needs the commit processed, waiting for more eats into its window
+    send_with_commitment(rpc, claimer, &instructions, CommitmentConfig::processed())?;
+    Ok(())
+}
+
+fn send(rpc: &RpcClient, payer: &Keypair, instructions: &[Instruction]) -> Result<Signature> {
+    send_with_commitment(rpc, payer, instructions, rpc.commitment())
+}
+
+fn send_with_commitment(rpc: &RpcClient, payer: &Keypair, instructions: &[Instruction], commitment: CommitmentConfig) -> Result<Signature> {
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        rpc.get_latest_blockhash()?,
+    );
+    Ok(rpc.send_and_confirm_transaction_with_spinner_and_commitment(&transaction, commitment)?)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
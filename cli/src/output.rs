@@ -0,0 +1,233 @@
+//! Table and JSON rendering of red packets, claims and transactions.
+
+use redpacket_client::{
+    AssetKind, ClaimAuthorization, RandomSplit, RandomnessSource, RedPacket, RedPacketAddress, SplitMode,
+};
+use redpacket_signer::SignedClaimResponse;
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::OutputFormat;
+
+pub struct Claim {
+    pub claimer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    // only claim receipts record when the claim happened
+    pub claim_time: Option<u64>,
+}
+
+pub fn claims_from_records(red_packet: &RedPacket) -> Vec<Claim> {
+    red_packet
+        .claimed_users
+        .iter()
+        .zip(&red_packet.claimed_recipients)
+        .zip(&red_packet.claimed_amount_records)
+        .map(|((claimer, recipient), amount)| Claim {
+            claimer: *claimer,
+            recipient: *recipient,
+            amount: *amount,
+            claim_time: None,
+        })
+        .collect()
+}
+
+pub fn print_transaction(format: OutputFormat, label: &str, address: &Pubkey, signature: &Signature) {
+    match format {
+        OutputFormat::Table => {
+            print_rows(&[(label.to_string(), address.to_string()), ("signature".into(), signature.to_string())])
+        }
+        OutputFormat::Json => print_json(&json!({ label: address.to_string(), "signature": signature.to_string() })),
+    }
+}
+
+pub fn print_signed_claim(format: OutputFormat, claim: &SignedClaimResponse) {
+    match format {
+        OutputFormat::Table => {
+            let mut rows = vec![
+                ("signer".into(), claim.signer.clone()),
+                ("signature".into(), claim.signature.clone()),
+                ("message (base64)".into(), claim.message.clone()),
+                ("ed25519 instruction (base64)".into(), claim.instruction_data.clone()),
+            ];
+            if let (Some(deadline), Some(max_amount)) = (claim.deadline, claim.max_amount) {
+                rows.push(("deadline".into(), deadline.to_string()));
+                rows.push(("max amount".into(), max_amount.to_string()));
+            }
+            print_rows(&rows)
+        }
+        OutputFormat::Json => print_json(&serde_json::to_value(claim).expect("serializable")),
+    }
+}
+
+pub fn print_red_packet(format: OutputFormat, address: &Pubkey, red_packet: &RedPacket) {
+    let claims = claims_from_records(red_packet);
+    match format {
+        OutputFormat::Table => {
+            let expiry = red_packet.create_time.saturating_add(red_packet.duration);
+            print_rows(&[
+                ("address".into(), address.to_string()),
+                ("creator".into(), red_packet.creator.to_string()),
+                ("address seed".into(), address_seed_label(&red_packet.address)),
+                ("asset".into(), asset_label(red_packet)),
+                ("claimed".into(), format!("{} / {}", red_packet.claimed_number, red_packet.total_number)),
+                ("claimed amount".into(), format!("{} / {}", red_packet.claimed_amount, red_packet.total_amount)),
+                ("create time".into(), red_packet.create_time.to_string()),
+                ("open time".into(), red_packet.open_time.to_string()),
+                ("expiry".into(), expiry.to_string()),
+                ("split mode".into(), split_mode_label(&red_packet.split_mode)),
+                ("claim authorization".into(), claim_authorization_label(red_packet)),
+                ("randomness".into(), randomness_label(red_packet)),
+                ("claim records".into(), if red_packet.use_claim_receipts { "receipts" } else { "account" }.into()),
+                ("cancelled".into(), red_packet.cancelled.to_string()),
+                ("claims paused".into(), red_packet.claims_paused.to_string()),
+                ("name".into(), red_packet.name.clone()),
+                ("message".into(), red_packet.message.clone()),
+            ]);
+            if red_packet.use_claim_receipts {
+                println!("\nclaims are recorded in claim receipts, see list-claims");
+            } else if !claims.is_empty() {
+                println!();
+                print_claims(format, &claims);
+            }
+        }
+        OutputFormat::Json => print_json(&json!({
+            "address": address.to_string(),
+            "creator": red_packet.creator.to_string(),
+            "address_seed": address_seed_label(&red_packet.address),
+            "token_type": match red_packet.token_type {
+                AssetKind::Native => "native",
+                AssetKind::SplToken => "spl_token",
+            },
+            "token_address": red_packet.token_address.to_string(),
+            "total_number": red_packet.total_number,
+            "claimed_number": red_packet.claimed_number,
+            "total_amount": red_packet.total_amount,
+            "claimed_amount": red_packet.claimed_amount,
+            "create_time": red_packet.create_time,
+            "create_slot": red_packet.create_slot,
+            "duration": red_packet.duration,
+            "open_time": red_packet.open_time,
+            "split_mode": split_mode_label(&red_packet.split_mode),
+            "claim_authorization": claim_authorization_label(red_packet),
+            "randomness": randomness_label(red_packet),
+            "use_claim_receipts": red_packet.use_claim_receipts,
+            "cancelled": red_packet.cancelled,
+            "claims_paused": red_packet.claims_paused,
+            "name": red_packet.name,
+            "message": red_packet.message,
+            "claims": claims_json(&claims),
+        })),
+    }
+}
+
+pub fn print_claims(format: OutputFormat, claims: &[Claim]) {
+    match format {
+        OutputFormat::Table => {
+            println!("{:>4}  {:<44}  {:<44}  {:>20}  {:>10}", "#", "claimer", "recipient", "amount", "time");
+            for (index, claim) in claims.iter().enumerate() {
+                let time = claim.claim_time.map(|time| time.to_string()).unwrap_or_else(|| "-".into());
+                println!(
+                    "{:>4}  {:<44}  {:<44}  {:>20}  {:>10}",
+                    index + 1,
+                    claim.claimer.to_string(),
+                    claim.recipient.to_string(),
+                    claim.amount,
+                    time
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&claims_json(claims)),
+    }
+}
+
+fn claims_json(claims: &[Claim]) -> Value {
+    claims
+        .iter()
+        .map(|claim| {
+            json!({
+                "claimer": claim.claimer.to_string(),
+                "recipient": claim.recipient.to_string(),
+                "amount": claim.amount,
+                "claim_time": claim.claim_time,
+            })
+        })
+        .collect()
+}
+
+fn print_rows(rows: &[(String, String)]) {
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        println!("{label:<width$}  {value}");
+    }
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).expect("serializable"));
+}
+
+fn address_seed_label(address: &RedPacketAddress) -> String {
+    match address {
+        RedPacketAddress::CreateTime => "create_time".into(),
+        RedPacketAddress::Id { id } => format!("id {}", hex(id)),
+    }
+}
+
+fn asset_label(red_packet: &RedPacket) -> String {
+    match red_packet.token_type {
+        AssetKind::Native => "SOL".into(),
+        AssetKind::SplToken => format!("SPL token {}", red_packet.token_address),
+    }
+}
+
+fn split_mode_label(split_mode: &SplitMode) -> String {
+    match split_mode {
+        SplitMode::Equal => "equal".into(),
+        SplitMode::Random { split: RandomSplit::Legacy } => "random".into(),
+        SplitMode::Random { split: RandomSplit::DoubleAverage { min_share, max_share } } => {
+            format!("random double average (min {min_share}, max {max_share})")
+        }
+        SplitMode::FixedList { tiers, shuffled } => {
+            let tiers: Vec<_> = tiers
+                .iter()
+                .map(|tier| format!("{}x{} ({} claimed)", tier.count, tier.amount, tier.claimed))
+                .collect();
+            format!("fixed list{} [{}]", if *shuffled { " shuffled" } else { "" }, tiers.join(", "))
+        }
+        SplitMode::WeightedTiers { tiers, shuffled } => {
+            let tiers: Vec<_> = tiers
+                .iter()
+                .map(|tier| format!("{}x{}bps ({} claimed)", tier.count, tier.weight_bps, tier.claimed))
+                .collect();
+            format!("weighted tiers{} [{}]", if *shuffled { " shuffled" } else { "" }, tiers.join(", "))
+        }
+    }
+}
+
+pub fn claim_authorization_label(red_packet: &RedPacket) -> String {
+    match &red_packet.claim_authorization {
+        ClaimAuthorization::Signature => format!("signature by {}", red_packet.pubkey_for_claim_signature),
+        ClaimAuthorization::SignatureV1 => format!("v1 signature by {}", red_packet.pubkey_for_claim_signature),
+        ClaimAuthorization::MerkleAllowlist { root } => format!("merkle allowlist {}", hex(root)),
+        ClaimAuthorization::Password { hash } => format!("password {}", hex(hash)),
+        ClaimAuthorization::MultiSignature { signers, threshold } => {
+            let signers: Vec<String> = signers.iter().map(Pubkey::to_string).collect();
+            format!("{} of {} signatures by [{}]", threshold, signers.len(), signers.join(", "))
+        }
+        ClaimAuthorization::Secp256k1 { eth_address } => format!("secp256k1 signature by 0x{}", hex(eth_address)),
+    }
+}
+
+fn randomness_label(red_packet: &RedPacket) -> String {
+    match &red_packet.randomness {
+        RandomnessSource::Timestamp => "timestamp".into(),
+        RandomnessSource::SlotHashes { .. } => match &red_packet.random_seed {
+            Some(seed) => format!("slot hashes, seed {}", hex(seed)),
+            None => "slot hashes, seed not revealed".into(),
+        },
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,24 @@
+use anchor_lang::{AccountDeserialize, Result};
+use redpacket::{legacy::LegacyRedPacket, ClaimReceipt, PasswordCommit, RandomCommit, RedPacket};
+
+// The decoders check the account discriminator, so passing the wrong account fails
+pub fn decode_red_packet(mut data: &[u8]) -> Result<RedPacket> {
+    RedPacket::try_deserialize(&mut data)
+}
+
+// Red packets created by the first program version, see withdraw_legacy_red_packet
+pub fn decode_legacy_red_packet(data: &[u8]) -> Result<LegacyRedPacket> {
+    LegacyRedPacket::decode(data)
+}
+
+pub fn decode_claim_receipt(mut data: &[u8]) -> Result<ClaimReceipt> {
+    ClaimReceipt::try_deserialize(&mut data)
+}
+
+pub fn decode_password_commit(mut data: &[u8]) -> Result<PasswordCommit> {
+    PasswordCommit::try_deserialize(&mut data)
+}
+
+pub fn decode_random_commit(mut data: &[u8]) -> Result<RandomCommit> {
+    RandomCommit::try_deserialize(&mut data)
+}
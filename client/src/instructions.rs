@@ -0,0 +1,586 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{ed25519_program, instruction::Instruction, system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use redpacket::legacy::LegacyRedPacket;
+use redpacket::password::password_commitment;
+use redpacket::{accounts, instruction, AssetKind, ClaimAuthorization, ClaimProof, RandomnessSource, RedPacket, SplitModeInput, ID};
+
+use crate::pda::*;
+use crate::signature::{
+    ed25519_verify_instruction, ed25519_verify_instruction_multi, secp256k1_verify_instruction, ClaimSignature,
+    ClaimSignatures, Secp256k1ClaimSignature,
+};
+
+// What a red packet holds; the token program is either SPL Token or Token-2022
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Asset {
+    Native,
+    SplToken { mint: Pubkey, token_program: Pubkey },
+}
+
+impl Asset {
+    pub fn kind(&self) -> AssetKind {
+        match self {
+            Asset::Native => AssetKind::Native,
+            Asset::SplToken { .. } => AssetKind::SplToken,
+        }
+    }
+
+    // The asset of an existing red packet; token_program is ignored for native packets
+    pub fn of(red_packet: &RedPacket, token_program: Pubkey) -> Self {
+        match red_packet.token_type {
+            AssetKind::Native => Asset::Native,
+            AssetKind::SplToken => Asset::SplToken { mint: red_packet.token_address, token_program },
+        }
+    }
+
+    // Token accounts for the instruction, owner being the creator or the recipient
+    fn token_accounts(&self, owner: &Pubkey, red_packet: &Pubkey) -> TokenAccounts {
+        match *self {
+            Asset::Native => TokenAccounts::default(),
+            Asset::SplToken { mint, token_program } => TokenAccounts {
+                token_mint: Some(mint),
+                token_account: Some(get_associated_token_address_with_program_id(owner, &mint, &token_program)),
+                vault: Some(vault_address(red_packet, &mint, &token_program)),
+                token_program: Some(token_program),
+                associated_token_program: Some(associated_token::ID),
+            },
+        }
+    }
+}
+
+// The per-claimer accounts a claim needs, depending on the red packet's settings
+struct ClaimAccounts {
+    claim_receipt: Option<Pubkey>,
+    password_commit: Option<Pubkey>,
+    random_commit: Option<Pubkey>,
+}
+
+impl ClaimAccounts {
+    fn of(red_packet_address: &Pubkey, red_packet: &RedPacket, claimer: &Pubkey) -> Self {
+        ClaimAccounts {
+            claim_receipt: red_packet.use_claim_receipts.then(|| claim_receipt_address(red_packet_address, claimer).0),
+            password_commit: matches!(red_packet.claim_authorization, ClaimAuthorization::Password { .. })
+                .then(|| password_commit_address(red_packet_address, claimer).0),
+            random_commit: red_packet.needs_random_commit().then(|| random_commit_address(red_packet_address, claimer).0),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TokenAccounts {
+    token_mint: Option<Pubkey>,
+    token_account: Option<Pubkey>,
+    vault: Option<Pubkey>,
+    token_program: Option<Pubkey>,
+    associated_token_program: Option<Pubkey>,
+}
+
+// Arguments shared by create_red_packet and create_red_packet_with_id
+#[derive(Clone)]
+pub struct CreateParams {
+    pub total_number: u32,
+    pub total_amount: u64,
+    pub duration: u64,
+    pub open_time: u64,
+    pub split_mode: SplitModeInput,
+    pub pubkey_for_claim_signature: Pubkey,
+    pub name: String,
+    pub message: String,
+    pub use_claim_receipts: bool,
+    pub claim_authorization: ClaimAuthorization,
+    pub randomness: RandomnessSource,
+}
+
+pub fn create_red_packet(creator: &Pubkey, create_time: u64, asset: &Asset, params: CreateParams) -> Instruction {
+    let red_packet = red_packet_address(creator, create_time).0;
+    let token = asset.token_accounts(creator, &red_packet);
+    let accounts = accounts::CreateRedPacket {
+        signer: *creator,
+        red_packet,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::CreateRedPacket {
+        asset_kind: asset.kind(),
+        total_number: params.total_number,
+        total_amount: params.total_amount,
+        create_time,
+        duration: params.duration,
+        open_time: params.open_time,
+        split_mode: params.split_mode,
+        pubkey_for_claim_signature: params.pubkey_for_claim_signature,
+        name: params.name,
+        message: params.message,
+        use_claim_receipts: params.use_claim_receipts,
+        claim_authorization: params.claim_authorization,
+        randomness: params.randomness,
+    };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+pub fn create_red_packet_with_id(creator: &Pubkey, id: [u8; 32], asset: &Asset, params: CreateParams) -> Instruction {
+    let red_packet = red_packet_address_with_id(creator, &id).0;
+    let token = asset.token_accounts(creator, &red_packet);
+    let accounts = accounts::CreateRedPacketWithId {
+        signer: *creator,
+        red_packet,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::CreateRedPacketWithId {
+        id,
+        asset_kind: asset.kind(),
+        total_number: params.total_number,
+        total_amount: params.total_amount,
+        duration: params.duration,
+        open_time: params.open_time,
+        split_mode: params.split_mode,
+        pubkey_for_claim_signature: params.pubkey_for_claim_signature,
+        name: params.name,
+        message: params.message,
+        use_claim_receipts: params.use_claim_receipts,
+        claim_authorization: params.claim_authorization,
+        randomness: params.randomness,
+    };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// claim_red_packet for an existing red packet. The claim receipt, password commit and random
+// commit accounts are filled in from the packet's settings; with close_when_emptied the creator account is
+// passed so the last claim closes the packet.
+pub fn claim_red_packet(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    asset: &Asset,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    proof: ClaimProof,
+    close_when_emptied: bool,
+) -> Instruction {
+    let token = asset.token_accounts(recipient, red_packet_address);
+    let claim = ClaimAccounts::of(red_packet_address, red_packet, claimer);
+    let accounts = accounts::ClaimRedPacket {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        recipient: *recipient,
+        creator: close_when_emptied.then_some(red_packet.creator),
+        claim_receipt: claim.claim_receipt,
+        password_commit: claim.password_commit,
+        random_commit: claim.random_commit,
+        token_mint: token.token_mint,
+        recipient_token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+        instructions: sysvar::instructions::ID,
+        ed25519_program: ed25519_program::ID,
+        slot_hashes: sysvar::slot_hashes::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::ClaimRedPacket { proof };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// The Ed25519 verify instruction followed by the claim, for red packets using
+// ClaimAuthorization::Signature or SignatureV1 (signature with terms).
+pub fn claim_red_packet_with_signature(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    asset: &Asset,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    signature: &ClaimSignature,
+    close_when_emptied: bool,
+) -> [Instruction; 2] {
+    let message = signature.message(red_packet_address, claimer, recipient);
+    [
+        ed25519_verify_instruction(&red_packet.pubkey_for_claim_signature, &message, &signature.signature),
+        claim_red_packet(red_packet_address, red_packet, asset, claimer, recipient, signature.proof(), close_when_emptied),
+    ]
+}
+
+// The multi-signature Ed25519 verify instruction followed by the claim, for red packets using
+// ClaimAuthorization::MultiSignature
+pub fn claim_red_packet_with_signatures(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    asset: &Asset,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    signatures: &ClaimSignatures,
+    close_when_emptied: bool,
+) -> [Instruction; 2] {
+    let message = signatures.message(red_packet_address, claimer, recipient);
+    [
+        ed25519_verify_instruction_multi(&message, &signatures.signatures),
+        claim_red_packet(red_packet_address, red_packet, asset, claimer, recipient, signatures.proof(), close_when_emptied),
+    ]
+}
+
+// The Secp256k1 verify instruction followed by the claim, for red packets using
+// ClaimAuthorization::Secp256k1. secp256k1_index is the position of the verify instruction in the
+// transaction, which the Secp256k1 program reads offsets against.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_red_packet_with_secp256k1_signature(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    asset: &Asset,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    signature: &Secp256k1ClaimSignature,
+    secp256k1_index: u8,
+    close_when_emptied: bool,
+) -> [Instruction; 2] {
+    let message = signature.message(red_packet_address, claimer, recipient);
+    [
+        secp256k1_verify_instruction(&signature.eth_address, &message, &signature.signature, signature.recovery_id, secp256k1_index),
+        claim_red_packet(red_packet_address, red_packet, asset, claimer, recipient, signature.proof(), close_when_emptied),
+    ]
+}
+
+pub fn withdraw_red_packet(red_packet_address: &Pubkey, red_packet: &RedPacket, asset: &Asset) -> Instruction {
+    let token = if red_packet.cancelled {
+        // the vault was already closed by cancel_red_packet
+        TokenAccounts::default()
+    } else {
+        asset.token_accounts(&red_packet.creator, red_packet_address)
+    };
+    let accounts = accounts::WithdrawRedPacket {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::WithdrawRedPacket {}.data(), accounts.to_account_metas(None))
+}
+
+// Refunds and closes a red packet created by the first program version
+pub fn withdraw_legacy_red_packet(red_packet_address: &Pubkey, red_packet: &LegacyRedPacket, asset: &Asset) -> Instruction {
+    let token = asset.token_accounts(&red_packet.creator, red_packet_address);
+    let accounts = accounts::WithdrawLegacyRedPacket {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::WithdrawLegacyRedPacket {}.data(), accounts.to_account_metas(None))
+}
+
+// Sent right before claim_red_packet on red packets where needs_random_commit() is set: the claim has
+// to land in one of the RANDOM_CLAIM_WINDOW_SLOTS slots after the commit
+pub fn commit_random_claim(red_packet_address: &Pubkey, claimer: &Pubkey) -> Instruction {
+    let accounts = accounts::CommitRandomClaim {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        random_commit: random_commit_address(red_packet_address, claimer).0,
+        system_program: system_program::ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::CommitRandomClaim {}.data(), accounts.to_account_metas(None))
+}
+
+// Frees a random commit that missed its claim window, once its slot hash has left SlotHashes
+pub fn close_random_commit(red_packet_address: &Pubkey, claimer: &Pubkey) -> Instruction {
+    let accounts = accounts::CloseRandomCommit {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        random_commit: random_commit_address(red_packet_address, claimer).0,
+    };
+    Instruction::new_with_bytes(ID, &instruction::CloseRandomCommit {}.data(), accounts.to_account_metas(None))
+}
+
+// Rotates the red packet's claim signing key (Signature and SignatureV1 packets), or pauses claims with None
+pub fn update_claim_signer(red_packet_address: &Pubkey, creator: &Pubkey, new_signer: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::UpdateClaimSigner {
+        signer: *creator,
+        red_packet: *red_packet_address,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::UpdateClaimSigner { new_signer };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// Replaces the claim authorization with one of the same kind, e.g. new MultiSignature signers
+pub fn update_claim_authorization(red_packet_address: &Pubkey, creator: &Pubkey, claim_authorization: ClaimAuthorization) -> Instruction {
+    let accounts = accounts::UpdateClaimSigner {
+        signer: *creator,
+        red_packet: *red_packet_address,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::UpdateClaimAuthorization { claim_authorization };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// Returns the claimer's receipt rent once the red packet account has been closed
+pub fn close_claim_receipt(red_packet_address: &Pubkey, claimer: &Pubkey) -> Instruction {
+    let accounts = accounts::CloseClaimReceipt {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        claim_receipt: claim_receipt_address(red_packet_address, claimer).0,
+    };
+    Instruction::new_with_bytes(ID, &instruction::CloseClaimReceipt {}.data(), accounts.to_account_metas(None))
+}
+
+// Adds shares and funds to a live red packet, paid by its creator
+pub fn top_up_red_packet(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    asset: &Asset,
+    additional_number: u32,
+    additional_amount: u64,
+) -> Instruction {
+    let token = asset.token_accounts(&red_packet.creator, red_packet_address);
+    let accounts = accounts::TopUpRedPacket {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::TopUpRedPacket { additional_number, additional_amount };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+pub fn update_red_packet_expiry(red_packet_address: &Pubkey, creator: &Pubkey, new_expiry: u64) -> Instruction {
+    let accounts = accounts::UpdateRedPacketExpiry {
+        signer: *creator,
+        red_packet: *red_packet_address,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::UpdateRedPacketExpiry { new_expiry };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// Refunds the unclaimed remainder of a live red packet and stops its claims; withdraw_red_packet
+// reclaims the rent afterwards
+pub fn cancel_red_packet(red_packet_address: &Pubkey, red_packet: &RedPacket, asset: &Asset) -> Instruction {
+    let token = asset.token_accounts(&red_packet.creator, red_packet_address);
+    let accounts = accounts::WithdrawRedPacket {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        token_mint: token.token_mint,
+        token_account: token.token_account,
+        vault: token.vault,
+        token_program: token.token_program,
+        associated_token_program: token.associated_token_program,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::CancelRedPacket {}.data(), accounts.to_account_metas(None))
+}
+
+// First step of a claim on a ClaimAuthorization::Password red packet: commits to the phrase
+// without revealing it, the claim then carries ClaimProof::Password with the phrase
+pub fn commit_password_claim(red_packet_address: &Pubkey, claimer: &Pubkey, phrase: &str) -> Instruction {
+    let accounts = accounts::CommitPasswordClaim {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        password_commit: password_commit_address(red_packet_address, claimer).0,
+        system_program: system_program::ID,
+    };
+    let data = instruction::CommitPasswordClaim { commitment: password_commitment(red_packet_address, claimer, phrase) };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// Reveals the seed committed to by RandomnessSource::SlotHashes, before random_seed_reveal_deadline
+pub fn reveal_random_seed(red_packet_address: &Pubkey, creator: &Pubkey, seed: [u8; 32]) -> Instruction {
+    let accounts = accounts::RevealRandomSeed {
+        signer: *creator,
+        red_packet: *red_packet_address,
+    };
+    Instruction::new_with_bytes(ID, &instruction::RevealRandomSeed { seed }.data(), accounts.to_account_metas(None))
+}
+
+// The instructions below are the per-asset ones of the first program version, kept for existing
+// clients; new code uses create_red_packet, claim_red_packet and withdraw_red_packet.
+
+pub fn create_red_packet_with_spl_token(
+    creator: &Pubkey,
+    create_time: u64,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    params: CreateParams,
+) -> Instruction {
+    let red_packet = red_packet_address(creator, create_time).0;
+    let accounts = accounts::CreateRedPacketWithSPLToken {
+        signer: *creator,
+        red_packet,
+        token_mint: *mint,
+        token_account: get_associated_token_address_with_program_id(creator, mint, token_program),
+        vault: vault_address(&red_packet, mint, token_program),
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::CreateRedPacketWithSplToken {
+        total_number: params.total_number,
+        total_amount: params.total_amount,
+        create_time,
+        duration: params.duration,
+        open_time: params.open_time,
+        split_mode: params.split_mode,
+        pubkey_for_claim_signature: params.pubkey_for_claim_signature,
+        name: params.name,
+        message: params.message,
+        use_claim_receipts: params.use_claim_receipts,
+        claim_authorization: params.claim_authorization,
+        randomness: params.randomness,
+    };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+pub fn create_red_packet_with_native_token(creator: &Pubkey, create_time: u64, params: CreateParams) -> Instruction {
+    let accounts = accounts::CreateRedPacketWithNativeToken {
+        signer: *creator,
+        red_packet: red_packet_address(creator, create_time).0,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::CreateRedPacketWithNativeToken {
+        total_number: params.total_number,
+        total_amount: params.total_amount,
+        create_time,
+        duration: params.duration,
+        open_time: params.open_time,
+        split_mode: params.split_mode,
+        pubkey_for_claim_signature: params.pubkey_for_claim_signature,
+        name: params.name,
+        message: params.message,
+        use_claim_receipts: params.use_claim_receipts,
+        claim_authorization: params.claim_authorization,
+        randomness: params.randomness,
+    };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+// claim_with_spl_token, with the optional accounts filled in like claim_red_packet
+pub fn claim_with_spl_token(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    proof: ClaimProof,
+) -> Instruction {
+    let claim = ClaimAccounts::of(red_packet_address, red_packet, claimer);
+    let accounts = accounts::ClaimRedPacketWithSPLToken {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        token_mint: *mint,
+        recipient: *recipient,
+        claim_receipt: claim.claim_receipt,
+        password_commit: claim.password_commit,
+        random_commit: claim.random_commit,
+        recipient_token_account: get_associated_token_address_with_program_id(recipient, mint, token_program),
+        vault: vault_address(red_packet_address, mint, token_program),
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        instructions: sysvar::instructions::ID,
+        ed25519_program: ed25519_program::ID,
+        slot_hashes: sysvar::slot_hashes::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::ClaimWithSplToken { proof };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+pub fn claim_with_native_token(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    proof: ClaimProof,
+) -> Instruction {
+    let claim = ClaimAccounts::of(red_packet_address, red_packet, claimer);
+    let accounts = accounts::ClaimRedPacketWithNativeToken {
+        signer: *claimer,
+        red_packet: *red_packet_address,
+        recipient: *recipient,
+        claim_receipt: claim.claim_receipt,
+        password_commit: claim.password_commit,
+        random_commit: claim.random_commit,
+        system_program: system_program::ID,
+        instructions: sysvar::instructions::ID,
+        ed25519_program: ed25519_program::ID,
+        slot_hashes: sysvar::slot_hashes::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    let data = instruction::ClaimWithNativeToken { proof };
+    Instruction::new_with_bytes(ID, &data.data(), accounts.to_account_metas(None))
+}
+
+pub fn withdraw_with_spl_token(
+    red_packet_address: &Pubkey,
+    red_packet: &RedPacket,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let accounts = accounts::RedPacketWithSPLToken {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        token_mint: *mint,
+        token_account: get_associated_token_address_with_program_id(&red_packet.creator, mint, token_program),
+        vault: vault_address(red_packet_address, mint, token_program),
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        instructions: sysvar::instructions::ID,
+        ed25519_program: ed25519_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::WithdrawWithSplToken {}.data(), accounts.to_account_metas(None))
+}
+
+pub fn withdraw_with_native_token(red_packet_address: &Pubkey, red_packet: &RedPacket) -> Instruction {
+    let accounts = accounts::RedPacketWithNativeToken {
+        signer: red_packet.creator,
+        red_packet: *red_packet_address,
+        system_program: system_program::ID,
+        instructions: sysvar::instructions::ID,
+        ed25519_program: ed25519_program::ID,
+        event_authority: event_authority_address(),
+        program: ID,
+    };
+    Instruction::new_with_bytes(ID, &instruction::WithdrawWithNativeToken {}.data(), accounts.to_account_metas(None))
+}
@@ -0,0 +1,19 @@
+//! Client helpers for the redpacket program: PDA derivation, instruction builders
+//! (including the Ed25519 verify instruction the claim signature check expects) and
+//! account decoding. All types come from the program crate itself.
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod signature;
+
+pub use accounts::*;
+pub use instructions::*;
+pub use pda::*;
+pub use signature::*;
+
+pub use redpacket::{
+    self, AssetKind, ClaimAuthorization, ClaimProof, ClaimReceipt, PasswordCommit, RandomCommit, RandomSplit,
+    RandomnessSource, RedPacket, RedPacketAddress, ShareTier, ShareTierInput, SplitMode, SplitModeInput, WeightTier,
+    WeightTierInput, ID as PROGRAM_ID,
+};
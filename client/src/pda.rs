@@ -0,0 +1,35 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use redpacket::{CLAIM_RECEIPT_SEED, PASSWORD_COMMIT_SEED, RANDOM_COMMIT_SEED, ID};
+
+// seeds = [creator, create_time.to_le_bytes()]
+pub fn red_packet_address(creator: &Pubkey, create_time: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[creator.as_ref(), &create_time.to_le_bytes()], &ID)
+}
+
+// seeds = [creator, id], used by create_red_packet_with_id
+pub fn red_packet_address_with_id(creator: &Pubkey, id: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[creator.as_ref(), id.as_ref()], &ID)
+}
+
+pub fn claim_receipt_address(red_packet: &Pubkey, claimer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CLAIM_RECEIPT_SEED, red_packet.as_ref(), claimer.as_ref()], &ID)
+}
+
+pub fn password_commit_address(red_packet: &Pubkey, claimer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PASSWORD_COMMIT_SEED, red_packet.as_ref(), claimer.as_ref()], &ID)
+}
+
+pub fn random_commit_address(red_packet: &Pubkey, claimer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RANDOM_COMMIT_SEED, red_packet.as_ref(), claimer.as_ref()], &ID)
+}
+
+// The vault is the red packet's associated token account
+pub fn vault_address(red_packet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(red_packet, mint, token_program)
+}
+
+// Signs the self-CPI that emit_cpi! uses to log events
+pub fn event_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &ID).0
+}
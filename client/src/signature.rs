@@ -0,0 +1,184 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{ed25519_program, instruction::Instruction, secp256k1_program};
+use anchor_lang::AnchorSerialize;
+use redpacket::claim_message::{claim_message_v1 as program_claim_message_v1, legacy_claim_message};
+use redpacket::{ClaimProof, ID};
+use redpacket::ed25519::{
+    Ed25519SignatureOffsets, CURRENT_INSTRUCTION, PUBLIC_KEY_SIZE, SIGNATURE_OFFSETS_SIZE, SIGNATURE_OFFSETS_START,
+    SIGNATURE_SIZE,
+};
+use redpacket::secp256k1::{self, Secp256k1SignatureOffsets, ETH_ADDRESS_SIZE};
+
+// The message the claim signer signs for ClaimAuthorization::Signature, checked by
+// verify_claim_signature: red_packet || claimer || recipient
+pub fn claim_message(red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+    legacy_claim_message(red_packet, claimer, recipient)
+}
+
+// What a ClaimAuthorization::SignatureV1 signature covers besides the claim itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimTerms {
+    pub deadline: u64,   // unix time, inclusive
+    pub max_amount: u64, // 0 for no cap
+}
+
+// The message the claim signer signs for ClaimAuthorization::SignatureV1, bound to this program id
+pub fn claim_message_v1(red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey, terms: &ClaimTerms) -> Vec<u8> {
+    program_claim_message_v1(&ID, red_packet, claimer, recipient, terms.deadline, terms.max_amount)
+}
+
+// A claim signer's signature, with terms for ClaimAuthorization::SignatureV1 and without for
+// ClaimAuthorization::Signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimSignature {
+    pub signature: [u8; 64],
+    pub terms: Option<ClaimTerms>,
+}
+
+impl ClaimSignature {
+    pub fn message(&self, red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+        signed_message(self.terms.as_ref(), red_packet, claimer, recipient)
+    }
+
+    // The claim_red_packet proof carrying the signed terms
+    pub fn proof(&self) -> ClaimProof {
+        signed_proof(self.terms)
+    }
+}
+
+// Signatures of several claim signers over the same claim message, for
+// ClaimAuthorization::MultiSignature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimSignatures {
+    pub signatures: Vec<(Pubkey, [u8; 64])>,
+    pub terms: Option<ClaimTerms>,
+}
+
+impl ClaimSignatures {
+    pub fn message(&self, red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+        signed_message(self.terms.as_ref(), red_packet, claimer, recipient)
+    }
+
+    pub fn proof(&self) -> ClaimProof {
+        signed_proof(self.terms)
+    }
+}
+
+// A secp256k1 signature over keccak256 of the claim message, for ClaimAuthorization::Secp256k1
+// with the signer's Ethereum address. personal_sign is set when a wallet signed the message with
+// personal_sign, which hashes it behind the EIP-191 prefix. recovery_id is 0 or 1, a wallet
+// signature's v - 27.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Secp256k1ClaimSignature {
+    pub eth_address: [u8; 20],
+    pub signature: [u8; 64],
+    pub recovery_id: u8,
+    pub terms: Option<ClaimTerms>,
+    pub personal_sign: bool,
+}
+
+impl Secp256k1ClaimSignature {
+    // The message the Secp256k1 program hashes, prefixed for personal_sign signatures
+    pub fn message(&self, red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+        let message = signed_message(self.terms.as_ref(), red_packet, claimer, recipient);
+        if self.personal_sign {
+            secp256k1::personal_sign_message(&message)
+        } else {
+            message
+        }
+    }
+
+    pub fn proof(&self) -> ClaimProof {
+        signed_proof(self.terms)
+    }
+}
+
+fn signed_message(terms: Option<&ClaimTerms>, red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+    match terms {
+        Some(terms) => claim_message_v1(red_packet, claimer, recipient, terms),
+        None => claim_message(red_packet, claimer, recipient),
+    }
+}
+
+fn signed_proof(terms: Option<ClaimTerms>) -> ClaimProof {
+    match terms {
+        Some(ClaimTerms { deadline, max_amount }) => ClaimProof::SignatureV1 { deadline, max_amount },
+        None => ClaimProof::None,
+    }
+}
+
+// Ed25519 program instruction verifying one signature, with the public key, signature and
+// message stored inline in that order. It has to be placed right before the claim instruction.
+pub fn ed25519_verify_instruction(public_key: &Pubkey, message: &[u8], signature: &[u8; 64]) -> Instruction {
+    ed25519_verify_instruction_multi(message, &[(*public_key, *signature)])
+}
+
+// Ed25519 program instruction verifying several signatures over one message: the offsets, then
+// each public key followed by its signature, then the message they all point at.
+pub fn ed25519_verify_instruction_multi(message: &[u8], signatures: &[(Pubkey, [u8; 64])]) -> Instruction {
+    let keys_offset = SIGNATURE_OFFSETS_START + signatures.len() * SIGNATURE_OFFSETS_SIZE;
+    let message_offset = keys_offset + signatures.len() * (PUBLIC_KEY_SIZE + SIGNATURE_SIZE);
+    let mut data = Vec::with_capacity(message_offset + message.len());
+    data.extend_from_slice(&[signatures.len() as u8, 0]); // number of signatures, padding
+    for index in 0..signatures.len() {
+        let public_key_offset = keys_offset + index * (PUBLIC_KEY_SIZE + SIGNATURE_SIZE);
+        let offsets = Ed25519SignatureOffsets {
+            signature_offset: (public_key_offset + PUBLIC_KEY_SIZE) as u16,
+            signature_instruction_index: CURRENT_INSTRUCTION,
+            public_key_offset: public_key_offset as u16,
+            public_key_instruction_index: CURRENT_INSTRUCTION,
+            message_data_offset: message_offset as u16,
+            message_data_size: message.len() as u16,
+            message_instruction_index: CURRENT_INSTRUCTION,
+        };
+        data.extend_from_slice(&offsets.try_to_vec().unwrap());
+    }
+    for (public_key, signature) in signatures {
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(signature);
+    }
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+// Secp256k1 program instruction verifying one signature, with the Ethereum address, signature,
+// recovery id and message stored inline in that order. instruction_index is the position the
+// instruction will have in the transaction, right before the claim instruction.
+pub fn secp256k1_verify_instruction(
+    eth_address: &[u8; 20],
+    message: &[u8],
+    signature: &[u8; 64],
+    recovery_id: u8,
+    instruction_index: u8,
+) -> Instruction {
+    let eth_address_offset = secp256k1::SIGNATURE_OFFSETS_START + secp256k1::SIGNATURE_OFFSETS_SIZE;
+    let signature_offset = eth_address_offset + ETH_ADDRESS_SIZE;
+    let message_offset = signature_offset + secp256k1::SIGNATURE_SIZE + secp256k1::RECOVERY_ID_SIZE;
+    let mut data = Vec::with_capacity(message_offset + message.len());
+    data.push(1); // one signature
+    let offsets = Secp256k1SignatureOffsets {
+        signature_offset: signature_offset as u16,
+        signature_instruction_index: instruction_index,
+        eth_address_offset: eth_address_offset as u16,
+        eth_address_instruction_index: instruction_index,
+        message_data_offset: message_offset as u16,
+        message_data_size: message.len() as u16,
+        message_instruction_index: instruction_index,
+    };
+    data.extend_from_slice(&offsets.try_to_vec().unwrap());
+    data.extend_from_slice(eth_address);
+    data.extend_from_slice(signature);
+    data.push(recovery_id);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: secp256k1_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
@@ -0,0 +1,375 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{instruction::Instruction, system_program, sysvar};
+use anchor_spl::associated_token;
+use redpacket_client::*;
+
+const TOKEN_PROGRAM: Pubkey = anchor_spl::token_2022::ID;
+
+fn red_packet(creator: Pubkey, address: RedPacketAddress, create_time: u64) -> RedPacket {
+    RedPacket {
+        creator,
+        address,
+        bump: 255,
+        total_number: 3,
+        claimed_number: 0,
+        total_amount: 3_000,
+        claimed_amount: 0,
+        create_time,
+        create_slot: 1,
+        duration: 86_400,
+        open_time: create_time,
+        token_type: AssetKind::Native,
+        token_address: Pubkey::default(),
+        split_mode: SplitMode::Equal,
+        use_claim_receipts: false,
+        claimed_users: vec![],
+        claimed_recipients: vec![],
+        claimed_amount_records: vec![],
+        pubkey_for_claim_signature: Pubkey::new_unique(),
+        claim_authorization: ClaimAuthorization::Signature,
+        randomness: RandomnessSource::Timestamp,
+        random_seed: None,
+        cancelled: false,
+        claims_paused: false,
+        name: "red packet".into(),
+        message: "red packet".into(),
+    }
+}
+
+fn spl_red_packet(creator: Pubkey, mint: Pubkey) -> RedPacket {
+    RedPacket { token_type: AssetKind::SplToken, token_address: mint, ..red_packet(creator, RedPacketAddress::CreateTime, 1_700_000_000) }
+}
+
+// Associated token address derived independently of the client's helpers
+fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[owner.as_ref(), TOKEN_PROGRAM.as_ref(), mint.as_ref()], &associated_token::ID).0
+}
+
+// Anchor passes the program id in place of an optional account that is left out
+fn optional_account(instruction: &Instruction, index: usize) -> Option<Pubkey> {
+    let pubkey = instruction.accounts[index].pubkey;
+    (pubkey != PROGRAM_ID).then_some(pubkey)
+}
+
+fn account_keys(instruction: &Instruction, range: std::ops::Range<usize>) -> Vec<Pubkey> {
+    instruction.accounts[range].iter().map(|meta| meta.pubkey).collect()
+}
+
+// ClaimRedPacket account order
+const CLAIM_CREATOR: usize = 3;
+const CLAIM_RECEIPT: usize = 4;
+const CLAIM_PASSWORD_COMMIT: usize = 5;
+const CLAIM_RANDOM_COMMIT: usize = 6;
+const CLAIM_TOKEN_MINT: usize = 7;
+const CLAIM_RECIPIENT_TOKEN_ACCOUNT: usize = 8;
+const CLAIM_VAULT: usize = 9;
+const CLAIM_TOKEN_PROGRAM: usize = 10;
+const CLAIM_ASSOCIATED_TOKEN_PROGRAM: usize = 11;
+
+#[test]
+fn red_packet_addresses_match_the_program_seeds() {
+    let creator = Pubkey::new_unique();
+    let create_time = 1_700_000_000;
+    let (address, bump) = red_packet_address(&creator, create_time);
+    let account = red_packet(creator, RedPacketAddress::CreateTime, create_time);
+    assert_eq!(Pubkey::create_program_address(&[creator.as_ref(), &account.address_seed(), &[bump]], &PROGRAM_ID).unwrap(), address);
+
+    let id = [9; 32];
+    let (address_with_id, bump) = red_packet_address_with_id(&creator, &id);
+    let account = red_packet(creator, RedPacketAddress::Id { id }, create_time);
+    assert_eq!(Pubkey::create_program_address(&[creator.as_ref(), &account.address_seed(), &[bump]], &PROGRAM_ID).unwrap(), address_with_id);
+    assert_ne!(address, address_with_id);
+    assert_ne!(address, red_packet_address(&creator, create_time + 1).0);
+}
+
+#[test]
+fn claimer_accounts_are_distinct_per_packet_and_claimer() {
+    let (red_packet, other_red_packet) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (claimer, other_claimer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for address in [claim_receipt_address, password_commit_address, random_commit_address] {
+        assert_ne!(address(&red_packet, &claimer).0, address(&red_packet, &other_claimer).0);
+        assert_ne!(address(&red_packet, &claimer).0, address(&other_red_packet, &claimer).0);
+    }
+    let claim_accounts = [
+        claim_receipt_address(&red_packet, &claimer).0,
+        password_commit_address(&red_packet, &claimer).0,
+        random_commit_address(&red_packet, &claimer).0,
+    ];
+    assert_ne!(claim_accounts[0], claim_accounts[1]);
+    assert_ne!(claim_accounts[1], claim_accounts[2]);
+
+    let mint = Pubkey::new_unique();
+    assert_eq!(vault_address(&red_packet, &mint, &TOKEN_PROGRAM), associated_token_address(&red_packet, &mint));
+}
+
+#[test]
+fn native_claim_leaves_token_accounts_out() {
+    let (creator, claimer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let address = red_packet_address(&creator, 1_700_000_000).0;
+    let account = red_packet(creator, RedPacketAddress::CreateTime, 1_700_000_000);
+    let instruction = claim_red_packet(&address, &account, &Asset::Native, &claimer, &recipient, ClaimProof::None, false);
+
+    assert_eq!(instruction.program_id, PROGRAM_ID);
+    assert_eq!(account_keys(&instruction, 0..3), [claimer, address, recipient]);
+    for index in CLAIM_CREATOR..=CLAIM_ASSOCIATED_TOKEN_PROGRAM {
+        assert_eq!(optional_account(&instruction, index), None, "account {index}");
+    }
+    assert_eq!(
+        account_keys(&instruction, 12..16),
+        [system_program::ID, sysvar::instructions::ID, anchor_lang::solana_program::ed25519_program::ID, sysvar::slot_hashes::ID]
+    );
+    assert_eq!(optional_account(&instruction, 16), Some(event_authority_address()));
+}
+
+#[test]
+fn spl_claim_passes_the_recipient_token_account_and_vault() {
+    let (creator, claimer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let address = red_packet_address(&creator, 1_700_000_000).0;
+    let account = spl_red_packet(creator, mint);
+    let asset = Asset::SplToken { mint, token_program: TOKEN_PROGRAM };
+    let instruction = claim_red_packet(&address, &account, &asset, &claimer, &recipient, ClaimProof::None, true);
+
+    assert_eq!(optional_account(&instruction, CLAIM_CREATOR), Some(creator));
+    assert_eq!(optional_account(&instruction, CLAIM_TOKEN_MINT), Some(mint));
+    // the recipient, not the claimer, owns the receiving token account
+    assert_eq!(optional_account(&instruction, CLAIM_RECIPIENT_TOKEN_ACCOUNT), Some(associated_token_address(&recipient, &mint)));
+    assert_eq!(optional_account(&instruction, CLAIM_VAULT), Some(associated_token_address(&address, &mint)));
+    assert_eq!(optional_account(&instruction, CLAIM_TOKEN_PROGRAM), Some(TOKEN_PROGRAM));
+    assert_eq!(optional_account(&instruction, CLAIM_ASSOCIATED_TOKEN_PROGRAM), Some(associated_token::ID));
+    assert!(instruction.accounts[CLAIM_VAULT].is_writable);
+    assert!(instruction.accounts[CLAIM_RECIPIENT_TOKEN_ACCOUNT].is_writable);
+}
+
+#[test]
+fn claim_accounts_follow_the_packet_settings() {
+    let (creator, claimer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let address = red_packet_address(&creator, 1_700_000_000).0;
+    let account = RedPacket {
+        use_claim_receipts: true,
+        claim_authorization: ClaimAuthorization::Password { hash: [1; 32] },
+        split_mode: SplitMode::Random { split: RandomSplit::Legacy },
+        randomness: RandomnessSource::SlotHashes { seed_commitment: [2; 32] },
+        ..red_packet(creator, RedPacketAddress::CreateTime, 1_700_000_000)
+    };
+    let proof = ClaimProof::Password { phrase: "phrase".into() };
+    let instruction = claim_red_packet(&address, &account, &Asset::Native, &claimer, &claimer, proof, false);
+
+    assert_eq!(optional_account(&instruction, CLAIM_RECEIPT), Some(claim_receipt_address(&address, &claimer).0));
+    assert_eq!(optional_account(&instruction, CLAIM_PASSWORD_COMMIT), Some(password_commit_address(&address, &claimer).0));
+    assert_eq!(optional_account(&instruction, CLAIM_RANDOM_COMMIT), Some(random_commit_address(&address, &claimer).0));
+
+    // equal splits draw nothing, so they need no random commit even with slot hash randomness
+    let account = RedPacket { split_mode: SplitMode::Equal, ..account };
+    let instruction = claim_red_packet(&address, &account, &Asset::Native, &claimer, &claimer, ClaimProof::None, false);
+    assert_eq!(optional_account(&instruction, CLAIM_RANDOM_COMMIT), None);
+}
+
+#[test]
+fn create_and_withdraw_address_the_same_vault() {
+    let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let create_time = 1_700_000_000;
+    let asset = Asset::SplToken { mint, token_program: TOKEN_PROGRAM };
+    let params = CreateParams {
+        total_number: 3,
+        total_amount: 3_000,
+        duration: 86_400,
+        open_time: create_time,
+        split_mode: SplitModeInput::Equal,
+        pubkey_for_claim_signature: Pubkey::new_unique(),
+        name: "red packet".into(),
+        message: "red packet".into(),
+        use_claim_receipts: false,
+        claim_authorization: ClaimAuthorization::Signature,
+        randomness: RandomnessSource::Timestamp,
+    };
+    let create = create_red_packet(&creator, create_time, &asset, params);
+    let address = red_packet_address(&creator, create_time).0;
+    // signer, red_packet, token_mint, token_account, vault
+    assert_eq!(account_keys(&create, 0..5), [creator, address, mint, associated_token_address(&creator, &mint), associated_token_address(&address, &mint)]);
+
+    let account = spl_red_packet(creator, mint);
+    let withdraw = withdraw_red_packet(&address, &account, &asset);
+    assert_eq!(account_keys(&withdraw, 0..5), account_keys(&create, 0..5));
+
+    // cancel_red_packet already closed the vault
+    let cancelled = RedPacket { cancelled: true, ..account };
+    let withdraw = withdraw_red_packet(&address, &cancelled, &asset);
+    for index in 2..7 {
+        assert_eq!(optional_account(&withdraw, index), None, "account {index}");
+    }
+}
+
+fn all_keys(instruction: &Instruction) -> Vec<Pubkey> {
+    account_keys(instruction, 0..instruction.accounts.len())
+}
+
+// The accounts #[event_cpi] appends to an instruction
+fn with_event_accounts(mut keys: Vec<Pubkey>) -> Vec<Pubkey> {
+    keys.extend([event_authority_address(), PROGRAM_ID]);
+    keys
+}
+
+#[test]
+fn creator_instructions_list_their_accounts() {
+    let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let address = red_packet_address(&creator, 1_700_000_000).0;
+    let asset = Asset::SplToken { mint, token_program: TOKEN_PROGRAM };
+    let account = spl_red_packet(creator, mint);
+    let (creator_token_account, vault) = (associated_token_address(&creator, &mint), associated_token_address(&address, &mint));
+
+    let top_up = top_up_red_packet(&address, &account, &asset, 1, 1_000);
+    assert_eq!(
+        all_keys(&top_up),
+        with_event_accounts(vec![creator, address, mint, creator_token_account, vault, TOKEN_PROGRAM, system_program::ID])
+    );
+    assert!(top_up.accounts[0].is_signer && top_up.accounts[4].is_writable);
+    let native = red_packet(creator, RedPacketAddress::CreateTime, 1_700_000_000);
+    let top_up = top_up_red_packet(&address, &native, &Asset::Native, 1, 1_000);
+    for index in 2..6 {
+        assert_eq!(optional_account(&top_up, index), None, "account {index}");
+    }
+
+    let cancel = cancel_red_packet(&address, &account, &asset);
+    assert_eq!(
+        all_keys(&cancel),
+        with_event_accounts(vec![
+            creator,
+            address,
+            mint,
+            creator_token_account,
+            vault,
+            TOKEN_PROGRAM,
+            associated_token::ID,
+            system_program::ID
+        ])
+    );
+
+    for instruction in [
+        update_red_packet_expiry(&address, &creator, 1_800_000_000),
+        update_claim_signer(&address, &creator, None),
+        update_claim_authorization(&address, &creator, ClaimAuthorization::Secp256k1 { eth_address: [1; 20] }),
+    ] {
+        assert_eq!(all_keys(&instruction), with_event_accounts(vec![creator, address]));
+        assert!(instruction.accounts[0].is_signer && instruction.accounts[1].is_writable);
+    }
+    let reveal = reveal_random_seed(&address, &creator, [3; 32]);
+    assert_eq!(all_keys(&reveal), [creator, address]);
+    assert!(reveal.accounts[0].is_signer && reveal.accounts[1].is_writable);
+}
+
+#[test]
+fn claimer_instructions_list_their_accounts() {
+    let (red_packet, claimer) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let commit = commit_password_claim(&red_packet, &claimer, "phrase");
+    assert_eq!(all_keys(&commit), [claimer, red_packet, password_commit_address(&red_packet, &claimer).0, system_program::ID]);
+    // after the 8-byte discriminator
+    assert_eq!(commit.data[8..], redpacket::password::password_commitment(&red_packet, &claimer, "phrase"));
+
+    let commit = commit_random_claim(&red_packet, &claimer);
+    assert_eq!(all_keys(&commit), [claimer, red_packet, random_commit_address(&red_packet, &claimer).0, system_program::ID]);
+    let close = close_random_commit(&red_packet, &claimer);
+    assert_eq!(all_keys(&close), [claimer, red_packet, random_commit_address(&red_packet, &claimer).0]);
+    let close = close_claim_receipt(&red_packet, &claimer);
+    assert_eq!(all_keys(&close), [claimer, red_packet, claim_receipt_address(&red_packet, &claimer).0]);
+    for instruction in [commit, close] {
+        assert!(instruction.accounts[0].is_signer && instruction.accounts[0].is_writable);
+        assert!(instruction.accounts[2].is_writable);
+    }
+}
+
+#[test]
+fn first_version_instructions_list_their_accounts() {
+    let (creator, claimer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let create_time = 1_700_000_000;
+    let address = red_packet_address(&creator, create_time).0;
+    let vault = associated_token_address(&address, &mint);
+    let params = CreateParams {
+        total_number: 3,
+        total_amount: 3_000,
+        duration: 86_400,
+        open_time: create_time,
+        split_mode: SplitModeInput::Equal,
+        pubkey_for_claim_signature: Pubkey::new_unique(),
+        name: "red packet".into(),
+        message: "red packet".into(),
+        use_claim_receipts: false,
+        claim_authorization: ClaimAuthorization::Signature,
+        randomness: RandomnessSource::Timestamp,
+    };
+    let token_accounts = |owner: &Pubkey| vec![mint, associated_token_address(owner, &mint), vault, TOKEN_PROGRAM, associated_token::ID];
+
+    let create = create_red_packet_with_spl_token(&creator, create_time, &mint, &TOKEN_PROGRAM, params.clone());
+    let mut expected = vec![creator, address];
+    expected.extend(token_accounts(&creator));
+    expected.push(system_program::ID);
+    assert_eq!(all_keys(&create), with_event_accounts(expected));
+    let create = create_red_packet_with_native_token(&creator, create_time, params);
+    assert_eq!(all_keys(&create), with_event_accounts(vec![creator, address, system_program::ID]));
+
+    let verify_accounts = [sysvar::instructions::ID, anchor_lang::solana_program::ed25519_program::ID];
+    let account = RedPacket { use_claim_receipts: true, ..spl_red_packet(creator, mint) };
+    let claim = claim_with_spl_token(&address, &account, &mint, &TOKEN_PROGRAM, &claimer, &recipient, ClaimProof::None);
+    let mut expected = vec![claimer, address, mint, recipient, claim_receipt_address(&address, &claimer).0, PROGRAM_ID, PROGRAM_ID];
+    expected.extend(token_accounts(&recipient)[1..].iter().copied());
+    expected.push(system_program::ID);
+    expected.extend(verify_accounts);
+    expected.push(sysvar::slot_hashes::ID);
+    assert_eq!(all_keys(&claim), with_event_accounts(expected));
+    let native = red_packet(creator, RedPacketAddress::CreateTime, create_time);
+    let claim = claim_with_native_token(&address, &native, &claimer, &recipient, ClaimProof::None);
+    let mut expected = vec![claimer, address, recipient, PROGRAM_ID, PROGRAM_ID, PROGRAM_ID, system_program::ID];
+    expected.extend(verify_accounts);
+    expected.push(sysvar::slot_hashes::ID);
+    assert_eq!(all_keys(&claim), with_event_accounts(expected));
+
+    let withdraw = withdraw_with_spl_token(&address, &account, &mint, &TOKEN_PROGRAM);
+    let mut expected = vec![creator, address];
+    expected.extend(token_accounts(&creator));
+    expected.push(system_program::ID);
+    expected.extend(verify_accounts);
+    assert_eq!(all_keys(&withdraw), with_event_accounts(expected));
+    let withdraw = withdraw_with_native_token(&address, &native);
+    let mut expected = vec![creator, address, system_program::ID];
+    expected.extend(verify_accounts);
+    assert_eq!(all_keys(&withdraw), with_event_accounts(expected));
+}
+
+#[test]
+fn legacy_withdraw_lists_its_accounts() {
+    let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let create_time = 1_700_000_000;
+    let address = red_packet_address(&creator, create_time).0;
+    let legacy = redpacket::legacy::LegacyRedPacket {
+        creator,
+        total_number: 3,
+        claimed_number: 0,
+        total_amount: 3_000,
+        claimed_amount: 0,
+        create_time,
+        duration: 86_400,
+        token_type: redpacket::legacy::TOKEN_TYPE_SPL,
+        token_address: mint,
+        if_spilt_random: false,
+        claimed_users: vec![],
+        claimed_amount_records: vec![],
+        pubkey_for_claim_signature: Pubkey::new_unique(),
+        name: "legacy".into(),
+        message: "legacy".into(),
+    };
+    let asset = Asset::SplToken { mint, token_program: TOKEN_PROGRAM };
+    // no event accounts, like the first version's withdraw
+    assert_eq!(
+        all_keys(&withdraw_legacy_red_packet(&address, &legacy, &asset)),
+        [
+            creator,
+            address,
+            mint,
+            associated_token_address(&creator, &mint),
+            associated_token_address(&address, &mint),
+            TOKEN_PROGRAM,
+            associated_token::ID,
+            system_program::ID
+        ]
+    );
+}
@@ -0,0 +1,105 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::keccak;
+use redpacket_client::{
+    claim_message, claim_message_v1, redpacket::secp256k1, secp256k1_verify_instruction, ClaimProof, ClaimTerms, Secp256k1ClaimSignature,
+};
+use solana_sdk::{feature_set::FeatureSet, secp256k1_instruction};
+
+fn eth_key() -> (libsecp256k1::SecretKey, [u8; 20]) {
+    let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+    let eth_address = secp256k1_instruction::construct_eth_pubkey(&libsecp256k1::PublicKey::from_secret_key(&secret_key));
+    (secret_key, eth_address)
+}
+
+fn sign(secret_key: &libsecp256k1::SecretKey, message: &[u8]) -> ([u8; 64], u8) {
+    let digest = libsecp256k1::Message::parse(&keccak::hash(message).to_bytes());
+    let (signature, recovery_id) = libsecp256k1::sign(&digest, secret_key);
+    (signature.serialize(), recovery_id.serialize())
+}
+
+#[test]
+fn verify_instruction_passes_the_precompile_and_the_program_parser() {
+    let (secret_key, eth_address) = eth_key();
+    let (red_packet, claimer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let terms = ClaimTerms { deadline: 1_700_000_000, max_amount: 0 };
+    let message = claim_message_v1(&red_packet, &claimer, &recipient, &terms);
+    let (signature, recovery_id) = sign(&secret_key, &message);
+
+    // placed second in the transaction, after e.g. a compute budget instruction
+    let instruction = secp256k1_verify_instruction(&eth_address, &message, &signature, recovery_id, 1);
+    let other = [0u8; 4];
+    secp256k1_instruction::verify(&instruction.data, &[&other, &instruction.data], &FeatureSet::all_enabled()).unwrap();
+
+    let signatures = secp256k1::parse_instruction(&instruction, 1).unwrap();
+    assert_eq!(
+        signatures,
+        vec![secp256k1::Secp256k1Signature { eth_address: &eth_address, signature: &signature, recovery_id, message: &message }]
+    );
+    // the same data is rejected at any other position
+    assert_eq!(secp256k1::parse_instruction(&instruction, 0), Err(secp256k1::Secp256k1Error::ForeignInstructionIndex));
+}
+
+#[test]
+fn secp256k1_claim_signature_carries_the_terms() {
+    let (secret_key, eth_address) = eth_key();
+    let (red_packet, claimer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let terms = ClaimTerms { deadline: 1_700_000_000, max_amount: 500 };
+    let message = claim_message_v1(&red_packet, &claimer, &claimer, &terms);
+    let (signature, recovery_id) = sign(&secret_key, &message);
+    let signature = Secp256k1ClaimSignature { eth_address, signature, recovery_id, terms: Some(terms), personal_sign: false };
+
+    assert_eq!(signature.message(&red_packet, &claimer, &claimer), message);
+    assert!(matches!(signature.proof(), ClaimProof::SignatureV1 { deadline: 1_700_000_000, max_amount: 500 }));
+}
+
+fn hex(text: &str) -> Vec<u8> {
+    let text = text.trim_start_matches("0x");
+    (0..text.len()).step_by(2).map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap()).collect()
+}
+
+// web3.js' accounts.sign example: personal_sign of "Some data" by this key
+#[test]
+fn personal_sign_vector_recovers_to_the_wallet_address() {
+    let secret_key =
+        libsecp256k1::SecretKey::parse_slice(&hex("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"))
+            .unwrap();
+    let eth_address = hex("0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+    let signature = hex("0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c");
+    let message = secp256k1::personal_sign_message(b"Some data");
+    let message_hash = hex("0x1da44b586eb0729ff70a73c326926f6ed5a25f5b056e7f47fbc6e58d86871655");
+    assert_eq!(keccak::hash(&message).to_bytes().to_vec(), message_hash);
+
+    let recovery_id = signature[64] - 27;
+    let recovered = libsecp256k1::recover(
+        &libsecp256k1::Message::parse(&keccak::hash(&message).to_bytes()),
+        &libsecp256k1::Signature::parse_standard_slice(&signature[..64]).unwrap(),
+        &libsecp256k1::RecoveryId::parse(recovery_id).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(recovered, libsecp256k1::PublicKey::from_secret_key(&secret_key));
+    assert_eq!(secp256k1_instruction::construct_eth_pubkey(&recovered).to_vec(), eth_address);
+
+    // the precompile recovers the same address from the prefixed message
+    let instruction = secp256k1_verify_instruction(
+        &eth_address.try_into().unwrap(),
+        &message,
+        &signature[..64].try_into().unwrap(),
+        recovery_id,
+        0,
+    );
+    secp256k1_instruction::verify(&instruction.data, &[&instruction.data], &FeatureSet::all_enabled()).unwrap();
+}
+
+#[test]
+fn personal_sign_claim_signature_prefixes_the_claim_message() {
+    let (secret_key, eth_address) = eth_key();
+    let (red_packet, claimer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let message = secp256k1::personal_sign_message(&claim_message(&red_packet, &claimer, &claimer));
+    let (signature, recovery_id) = sign(&secret_key, &message);
+    let signature = Secp256k1ClaimSignature { eth_address, signature, recovery_id, terms: None, personal_sign: true };
+
+    assert_eq!(signature.message(&red_packet, &claimer, &claimer), message);
+    let instruction = secp256k1_verify_instruction(&eth_address, &message, &signature.signature, recovery_id, 0);
+    secp256k1_instruction::verify(&instruction.data, &[&instruction.data], &FeatureSet::all_enabled()).unwrap();
+    assert!(matches!(signature.proof(), ClaimProof::None));
+}
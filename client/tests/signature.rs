@@ -0,0 +1,34 @@
+use anchor_lang::prelude::Pubkey;
+use redpacket_client::{claim_message, ed25519_verify_instruction, ed25519_verify_instruction_multi, redpacket::ed25519};
+
+#[test]
+fn verify_instruction_passes_the_program_parser() {
+    let (red_packet, claimer, recipient, signer) =
+        (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let message = claim_message(&red_packet, &claimer, &recipient);
+    let instruction = ed25519_verify_instruction(&signer, &message, &[7; 64]);
+
+    let signatures = ed25519::parse_instruction(&instruction).unwrap();
+    assert_eq!(
+        signatures,
+        vec![ed25519::Ed25519Signature { public_key: signer.as_ref(), signature: &[7; 64], message: &message }]
+    );
+}
+
+#[test]
+fn multi_signature_instruction_shares_one_message() {
+    let (red_packet, claimer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let signers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let message = claim_message(&red_packet, &claimer, &recipient);
+    let signatures: Vec<(Pubkey, [u8; 64])> = signers.iter().enumerate().map(|(i, signer)| (*signer, [i as u8; 64])).collect();
+    let instruction = ed25519_verify_instruction_multi(&message, &signatures);
+
+    let parsed = ed25519::parse_instruction(&instruction).unwrap();
+    let expected: Vec<_> = signatures
+        .iter()
+        .map(|(signer, signature)| ed25519::Ed25519Signature { public_key: signer.as_ref(), signature, message: &message })
+        .collect();
+    assert_eq!(parsed, expected);
+    // a single signature keeps the layout of ed25519_verify_instruction
+    assert_eq!(ed25519_verify_instruction_multi(&message, &signatures[..1]), ed25519_verify_instruction(&signers[0], &message, &[0; 64]));
+}
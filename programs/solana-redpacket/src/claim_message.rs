@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+// Prefix of ClaimAuthorization::SignatureV1 messages. A leading 0xff byte can never start a
+// transaction message, and the version keeps later formats apart.
+pub const CLAIM_MESSAGE_V1_DOMAIN: &[u8] = b"\xffredpacket claim v1";
+
+// ClaimAuthorization::Signature: red_packet || claimer || recipient
+pub fn legacy_claim_message(red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> Vec<u8> {
+    [red_packet.as_ref(), claimer.as_ref(), recipient.as_ref()].concat()
+}
+
+// ClaimAuthorization::SignatureV1: domain || program_id || red_packet || claimer || recipient
+// || deadline || max_amount, integers u64 little endian. The signature is accepted up to and
+// including the deadline (unix time), and max_amount == 0 means the claim amount is not capped.
+pub fn claim_message_v1(
+    program_id: &Pubkey,
+    red_packet: &Pubkey,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+    deadline: u64,
+    max_amount: u64,
+) -> Vec<u8> {
+    [
+        CLAIM_MESSAGE_V1_DOMAIN,
+        program_id.as_ref(),
+        red_packet.as_ref(),
+        claimer.as_ref(),
+        recipient.as_ref(),
+        &deadline.to_le_bytes(),
+        &max_amount.to_le_bytes(),
+    ]
+    .concat()
+}
@@ -1,19 +1,39 @@
 use anchor_lang::prelude::*;
 
 #[constant]
-pub const RED_PACKET_SPILT_RANDOM: bool = true;
+pub const RED_PACKET_WITHDRAW_STATUS_NOT_WITHDRAW: u8 = 0;
 
 #[constant]
-pub const RED_PACKET_SPILT_EQUAL: bool = false;
+pub const RED_PACKET_WITHDRAW_STATUS_CLAIMED: u8 = 1;
 
+// Claims recorded inside the red packet account, which grows by one record per claim (see
+// RedPacket::space). Red packets with use_claim_receipts have no such limit.
 #[constant]
-pub const RED_PACKET_USE_NATIVE_TOKEN: u8 = 0;
+pub const RED_PACKET_MAX_CLAIMERS: u8 = 200;
 
 #[constant]
-pub const RED_PACKET_USE_CUSTOM_TOKEN: u8 = 1;
+pub const CLAIM_RECEIPT_SEED: &[u8] = b"claim_receipt";
 
 #[constant]
-pub const RED_PACKET_WITHDRAW_STATUS_NOT_WITHDRAW: u8 = 0;
+pub const PASSWORD_COMMIT_SEED: &[u8] = b"password_commit";
 
 #[constant]
-pub const RED_PACKET_WITHDRAW_STATUS_CLAIMED: u8 = 1;
+pub const RED_PACKET_MAX_SHARE_TIERS: u8 = 16;
+
+#[constant]
+pub const SPLIT_WEIGHT_TOTAL_BPS: u64 = 10_000;
+
+#[constant]
+pub const RED_PACKET_MAX_CLAIM_SIGNERS: u8 = 5;
+
+#[constant]
+pub const RANDOM_COMMIT_SEED: &[u8] = b"random_commit";
+
+// A claim drawing from a committed slot hash has to land within this many slots after the commit
+#[constant]
+pub const RANDOM_CLAIM_WINDOW_SLOTS: u64 = 2;
+
+// RandomnessSource::SlotHashes packets run without the creator seed if it is not revealed within
+// this many seconds after the packet opens
+#[constant]
+pub const RANDOM_SEED_REVEAL_WINDOW: u64 = 60 * 60;
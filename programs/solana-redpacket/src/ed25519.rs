@@ -0,0 +1,193 @@
+use anchor_lang::prelude::*;
+use solana_program::{ed25519_program, instruction::Instruction};
+
+// Ed25519 program instruction data: num_signatures: u8, padding: u8, then one
+// Ed25519SignatureOffsets per signature, then the public keys, signatures and messages
+// the offsets point at.
+pub const SIGNATURE_OFFSETS_START: usize = 2;
+pub const SIGNATURE_OFFSETS_SIZE: usize = 14;
+pub const PUBLIC_KEY_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 64;
+// instruction index meaning "the Ed25519 instruction itself"
+pub const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ed25519Error {
+    NotEd25519Program,
+    NoSignatures,
+    Truncated,
+    // The precompile would verify data from another instruction than the one parsed here
+    ForeignInstructionIndex,
+    OffsetOutOfBounds,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ed25519SignatureOffsets {
+    pub signature_offset: u16,             // offset to ed25519 signature of 64 bytes
+    pub signature_instruction_index: u16,  // instruction index to find signature
+    pub public_key_offset: u16,            // offset to public key of 32 bytes
+    pub public_key_instruction_index: u16, // instruction index to find public key
+    pub message_data_offset: u16,          // offset to start of message data
+    pub message_data_size: u16,            // size of message data
+    pub message_instruction_index: u16,    // index of instruction data to get message data
+}
+
+// One signature checked by the Ed25519 program, borrowed from its instruction data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ed25519Signature<'a> {
+    pub public_key: &'a [u8],
+    pub signature: &'a [u8],
+    pub message: &'a [u8],
+}
+
+// Parses an Ed25519 program instruction without panicking on any input. Every offset must stay
+// inside the instruction data and every instruction index must point at the instruction itself,
+// so what is returned is exactly what the precompile verified.
+pub fn parse_instruction(instruction: &Instruction) -> std::result::Result<Vec<Ed25519Signature<'_>>, Ed25519Error> {
+    if instruction.program_id != ed25519_program::ID {
+        return Err(Ed25519Error::NotEd25519Program);
+    }
+    let data = instruction.data.as_slice();
+    let num_signatures = *data.first().ok_or(Ed25519Error::Truncated)? as usize;
+    if num_signatures == 0 {
+        return Err(Ed25519Error::NoSignatures);
+    }
+    let offsets_end = SIGNATURE_OFFSETS_START + num_signatures * SIGNATURE_OFFSETS_SIZE;
+    let offsets_data = data.get(SIGNATURE_OFFSETS_START..offsets_end).ok_or(Ed25519Error::Truncated)?;
+
+    offsets_data
+        .chunks_exact(SIGNATURE_OFFSETS_SIZE)
+        .map(|chunk| {
+            let offsets = Ed25519SignatureOffsets::try_from_slice(chunk).map_err(|_| Ed25519Error::Truncated)?;
+            if offsets.signature_instruction_index != CURRENT_INSTRUCTION
+                || offsets.public_key_instruction_index != CURRENT_INSTRUCTION
+                || offsets.message_instruction_index != CURRENT_INSTRUCTION
+            {
+                return Err(Ed25519Error::ForeignInstructionIndex);
+            }
+            Ok(Ed25519Signature {
+                public_key: slice(data, offsets.public_key_offset, PUBLIC_KEY_SIZE)?,
+                signature: slice(data, offsets.signature_offset, SIGNATURE_SIZE)?,
+                message: slice(data, offsets.message_data_offset, offsets.message_data_size as usize)?,
+            })
+        })
+        .collect()
+}
+
+fn slice(data: &[u8], offset: u16, size: usize) -> std::result::Result<&[u8], Ed25519Error> {
+    let start = offset as usize;
+    data.get(start..start + size).ok_or(Ed25519Error::OffsetOutOfBounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: [u8; 32] = [1; 32];
+    const SIGNATURE: [u8; 64] = [2; 64];
+    const MESSAGE: &[u8] = b"red packet || claimer || recipient";
+
+    fn offsets(public_key_offset: u16, signature_offset: u16, message_data_offset: u16) -> Ed25519SignatureOffsets {
+        Ed25519SignatureOffsets {
+            signature_offset,
+            signature_instruction_index: CURRENT_INSTRUCTION,
+            public_key_offset,
+            public_key_instruction_index: CURRENT_INSTRUCTION,
+            message_data_offset,
+            message_data_size: MESSAGE.len() as u16,
+            message_instruction_index: CURRENT_INSTRUCTION,
+        }
+    }
+
+    // Same layout as the client's ed25519_verify_instruction
+    fn valid_offsets() -> Ed25519SignatureOffsets {
+        offsets(16, 48, 112)
+    }
+
+    fn instruction(num_signatures: u8, offsets: &[Ed25519SignatureOffsets], payload: &[&[u8]]) -> Instruction {
+        let mut data = vec![num_signatures, 0];
+        for offsets in offsets {
+            data.extend_from_slice(&offsets.try_to_vec().unwrap());
+        }
+        for bytes in payload {
+            data.extend_from_slice(bytes);
+        }
+        Instruction { program_id: ed25519_program::ID, accounts: vec![], data }
+    }
+
+    fn single(offsets: Ed25519SignatureOffsets) -> Instruction {
+        instruction(1, &[offsets], &[&PUBLIC_KEY, &SIGNATURE, MESSAGE])
+    }
+
+    #[test]
+    fn parses_single_signature() {
+        let instruction = single(valid_offsets());
+        let signatures = parse_instruction(&instruction).unwrap();
+        assert_eq!(
+            signatures,
+            vec![Ed25519Signature { public_key: &PUBLIC_KEY, signature: &SIGNATURE, message: MESSAGE }]
+        );
+    }
+
+    #[test]
+    fn rejects_other_programs() {
+        let mut instruction = single(valid_offsets());
+        instruction.program_id = Pubkey::new_unique();
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::NotEd25519Program));
+    }
+
+    #[test]
+    fn rejects_short_headers() {
+        let mut instruction = single(valid_offsets());
+        instruction.data = vec![];
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::Truncated));
+        instruction.data = vec![1, 0, 16, 0];
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::Truncated));
+    }
+
+    #[test]
+    fn rejects_zero_signatures() {
+        let instruction = instruction(0, &[], &[]);
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::NoSignatures));
+    }
+
+    #[test]
+    fn rejects_signature_count_beyond_offsets() {
+        // claims 255 signatures but carries one offsets block
+        let instruction = instruction(u8::MAX, &[valid_offsets()], &[&PUBLIC_KEY, &SIGNATURE, MESSAGE]);
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::Truncated));
+    }
+
+    #[test]
+    fn rejects_foreign_instruction_indexes() {
+        let cases = [
+            Ed25519SignatureOffsets { signature_instruction_index: 0, ..valid_offsets() },
+            Ed25519SignatureOffsets { public_key_instruction_index: 1, ..valid_offsets() },
+            Ed25519SignatureOffsets { message_instruction_index: u16::MAX - 1, ..valid_offsets() },
+        ];
+        for offsets in cases {
+            assert_eq!(parse_instruction(&single(offsets)), Err(Ed25519Error::ForeignInstructionIndex));
+        }
+    }
+
+    #[test]
+    fn rejects_offsets_past_the_data() {
+        let len = single(valid_offsets()).data.len() as u16;
+        let cases = [
+            Ed25519SignatureOffsets { public_key_offset: len - 31, ..valid_offsets() },
+            Ed25519SignatureOffsets { signature_offset: len, ..valid_offsets() },
+            Ed25519SignatureOffsets { message_data_offset: u16::MAX, ..valid_offsets() },
+            Ed25519SignatureOffsets { message_data_size: u16::MAX, ..valid_offsets() },
+        ];
+        for offsets in cases {
+            assert_eq!(parse_instruction(&single(offsets)), Err(Ed25519Error::OffsetOutOfBounds));
+        }
+    }
+
+    #[test]
+    fn rejects_any_bad_signature_among_several() {
+        let second = Ed25519SignatureOffsets { signature_offset: 500, ..offsets(30, 62, 126) };
+        let instruction = instruction(2, &[offsets(30, 62, 126), second], &[&PUBLIC_KEY, &SIGNATURE, MESSAGE]);
+        assert_eq!(parse_instruction(&instruction), Err(Ed25519Error::OffsetOutOfBounds));
+    }
+}
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::{CustomError, RedPacket};
+
+pub const TOKEN_TYPE_NATIVE: u8 = 0;
+pub const TOKEN_TYPE_SPL: u8 = 1;
+
+// RedPacket as stored by the first deployed version of the program, at seeds [creator, create_time].
+// These accounts share RedPacket's discriminator but not its layout, so Account<RedPacket> cannot
+// load them; they are only decoded here so their creators can withdraw them (withdraw_legacy_red_packet).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct LegacyRedPacket {
+    pub creator: Pubkey,
+    pub total_number: u8,
+    pub claimed_number: u8,
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub create_time: u64,
+    pub duration: u64,
+    pub token_type: u8, // TOKEN_TYPE_NATIVE or TOKEN_TYPE_SPL
+    pub token_address: Pubkey,
+    pub if_spilt_random: bool,
+    #[max_len(200)]
+    pub claimed_users: Vec<Pubkey>,
+    #[max_len(200)]
+    pub claimed_amount_records: Vec<u64>,
+    pub pubkey_for_claim_signature: Pubkey,
+    #[max_len(100)]
+    pub name: String,
+    #[max_len(200)]
+    pub message: String,
+}
+
+impl LegacyRedPacket {
+    // The first version always allocated this much, which no current RedPacket account matches
+    pub const ACCOUNT_SIZE: usize = 8 + Self::INIT_SPACE;
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        require!(data.len() == Self::ACCOUNT_SIZE && data[..8] == RedPacket::DISCRIMINATOR, CustomError::NotLegacyRedPacket);
+        Self::deserialize(&mut &data[8..]).map_err(|_| error!(CustomError::NotLegacyRedPacket))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_red_packet() -> LegacyRedPacket {
+        LegacyRedPacket {
+            creator: Pubkey::new_unique(),
+            total_number: 3,
+            claimed_number: 1,
+            total_amount: 3_000,
+            claimed_amount: 1_000,
+            create_time: 1_700_000_000,
+            duration: 86_400,
+            token_type: TOKEN_TYPE_SPL,
+            token_address: Pubkey::new_unique(),
+            if_spilt_random: false,
+            claimed_users: vec![Pubkey::new_unique()],
+            claimed_amount_records: vec![1_000],
+            pubkey_for_claim_signature: Pubkey::new_unique(),
+            name: "legacy".into(),
+            message: "legacy red packet".into(),
+        }
+    }
+
+    // Zero-padded to the allocated size, as the first version's accounts are
+    fn account_data(red_packet: &LegacyRedPacket) -> Vec<u8> {
+        let mut data = RedPacket::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&red_packet.try_to_vec().unwrap());
+        data.resize(LegacyRedPacket::ACCOUNT_SIZE, 0);
+        data
+    }
+
+    #[test]
+    fn decodes_first_version_accounts() {
+        let red_packet = legacy_red_packet();
+        assert_eq!(LegacyRedPacket::decode(&account_data(&red_packet)).unwrap(), red_packet);
+    }
+
+    #[test]
+    fn rejects_current_accounts_and_other_data() {
+        assert_ne!(LegacyRedPacket::ACCOUNT_SIZE, 8 + RedPacket::INIT_SPACE);
+        let data = account_data(&legacy_red_packet());
+        assert!(LegacyRedPacket::decode(&data[..data.len() - 1]).is_err());
+        let mut other = data.clone();
+        other[0] ^= 1;
+        assert!(LegacyRedPacket::decode(&other).is_err());
+        assert!(LegacyRedPacket::decode(&vec![0; LegacyRedPacket::ACCOUNT_SIZE]).is_err());
+    }
+}
@@ -1,4 +1,16 @@
+#![allow(clippy::too_many_arguments)]
+// anchor 0.30 macros check cfgs (anchor-debug, custom-heap, target_os = "solana", ...) that newer rustc does not know about
+#![allow(unexpected_cfgs)]
+
+pub mod claim_message;
 pub mod constants;
+pub mod ed25519;
+pub mod legacy;
+pub mod merkle;
+pub mod password;
+pub mod randomness;
+pub mod secp256k1;
+pub mod split;
 pub mod transfer;
 
 use anchor_lang::prelude::*;
@@ -10,6 +22,7 @@ use anchor_spl::{
 
 use solana_program::sysvar::instructions::{load_instruction_at_checked, load_current_index_checked};
 use solana_program::hash::hash;
+use solana_program::instruction::Instruction;
 
 
 pub use constants::*;
@@ -22,219 +35,429 @@ declare_id!("CXT16oAAbmgpPZsL2sGmfSUNrATk3AsFVU18thTUVNxx");
 pub mod redpacket {
     use super::*;
 
-    pub fn create_red_packet_with_spl_token(ctx: Context<CreateRedPacketWithSPLToken>, total_number: u8, total_amount: u64, create_time: u64, duration: u64, if_spilt_random: bool, pubkey_for_claim_signature: Pubkey, name: String, message: String) -> Result<()> {
-        // params check
-        require!(total_number > 0 && total_number <= 200, CustomError::InvalidTotalNumber);
-        require!(total_amount > 0 , CustomError::InvalidTotalAmount);
-        
-        // time check
-        let _current_time = Clock::get().unwrap().unix_timestamp; 
-        require!(_current_time.abs_diff(create_time as i64) < 120, CustomError::InvalidCreateTime);
-        require!(create_time + duration > _current_time as u64, CustomError::InvalidExpiryTime);
-
-        // check if the creator has enough tokens
-        require!(ctx.accounts.token_account.amount >= total_amount, CustomError::InsufficientTokenBalance);
-
-        transfer::transfer_tokens(
-            &ctx.accounts.token_account,
-            &ctx.accounts.vault,
-            &total_amount,
-            &ctx.accounts.token_mint,
-            &ctx.accounts.signer.to_account_info(),
-            &ctx.accounts.token_program,
-            &[]
-        )?;       
-        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, total_number, total_amount, create_time, duration, constants::RED_PACKET_USE_CUSTOM_TOKEN, ctx.accounts.token_mint.key(), if_spilt_random, pubkey_for_claim_signature, name, message);
+    pub fn create_red_packet(ctx: Context<CreateRedPacket>, asset_kind: AssetKind, total_number: u32, total_amount: u64, create_time: u64, duration: u64, open_time: u64, split_mode: SplitModeInput, pubkey_for_claim_signature: Pubkey, name: String, message: String, use_claim_receipts: bool, claim_authorization: ClaimAuthorization, randomness: RandomnessSource) -> Result<()> {
+        let split_mode = SplitMode::from(split_mode);
+        validate_create_params(total_number, total_amount, create_time, duration, open_time, &split_mode, use_claim_receipts, &claim_authorization)?;
+
+        let token_address = fund_red_packet(asset_kind, &ctx.accounts.signer, &ctx.accounts.red_packet, ctx.accounts.token_mint.as_ref(), ctx.accounts.token_account.as_ref(), ctx.accounts.vault.as_ref(), ctx.accounts.token_program.as_ref(), &ctx.accounts.system_program, total_amount)?;
+        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, RedPacketAddress::CreateTime, ctx.bumps.red_packet, total_number, total_amount, create_time, duration, open_time, asset_kind, token_address, split_mode, use_claim_receipts, pubkey_for_claim_signature, claim_authorization, randomness, name, message);
+        fund_claim_records(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, total_number)?;
+        emit_cpi!(created_event(&ctx.accounts.red_packet));
 
         Ok(())
     }
 
-    pub fn create_red_packet_with_native_token(ctx: Context<CreateRedPacketWithNativeToken>, total_number: u8, total_amount: u64, create_time: u64, duration: u64, if_spilt_random: bool, pubkey_for_claim_signature: Pubkey, name: String, message: String) -> Result<()> {
-        // params check
-        require!(total_number > 0 && total_number <= 200, CustomError::InvalidTotalNumber);
-        require!(total_amount > 0 , CustomError::InvalidTotalAmount);
-    
-        // time check
-        let _current_time = Clock::get().unwrap().unix_timestamp;
-        require!(_current_time.abs_diff(create_time as i64) < 120, CustomError::InvalidCreateTime);
-        require!(create_time + duration > _current_time as u64, CustomError::InvalidExpiryTime);
-
-        require!(ctx.accounts.signer.lamports() >= total_amount, CustomError::InsufficientTokenBalance);
-        // Transfer tokens from initializer to PDA account (red packet account)
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.signer.key(),
-            &ctx.accounts.red_packet.key(),
-            total_amount
-        );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                ctx.accounts.signer.to_account_info(),
-                ctx.accounts.red_packet.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
-
-        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, total_number, total_amount, create_time, duration, constants::RED_PACKET_USE_NATIVE_TOKEN, Pubkey::default(), if_spilt_random, pubkey_for_claim_signature, name, message);
+    // Same as create_red_packet, but the address is derived from a client-chosen id instead of
+    // create_time, which is taken from the cluster clock.
+    pub fn create_red_packet_with_id(ctx: Context<CreateRedPacketWithId>, id: [u8; 32], asset_kind: AssetKind, total_number: u32, total_amount: u64, duration: u64, open_time: u64, split_mode: SplitModeInput, pubkey_for_claim_signature: Pubkey, name: String, message: String, use_claim_receipts: bool, claim_authorization: ClaimAuthorization, randomness: RandomnessSource) -> Result<()> {
+        let create_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        let split_mode = SplitMode::from(split_mode);
+        validate_create_params(total_number, total_amount, create_time, duration, open_time, &split_mode, use_claim_receipts, &claim_authorization)?;
+
+        let token_address = fund_red_packet(asset_kind, &ctx.accounts.signer, &ctx.accounts.red_packet, ctx.accounts.token_mint.as_ref(), ctx.accounts.token_account.as_ref(), ctx.accounts.vault.as_ref(), ctx.accounts.token_program.as_ref(), &ctx.accounts.system_program, total_amount)?;
+        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, RedPacketAddress::Id { id }, ctx.bumps.red_packet, total_number, total_amount, create_time, duration, open_time, asset_kind, token_address, split_mode, use_claim_receipts, pubkey_for_claim_signature, claim_authorization, randomness, name, message);
+        fund_claim_records(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, total_number)?;
+        emit_cpi!(created_event(&ctx.accounts.red_packet));
+
+        Ok(())
+    }
+
+    pub fn create_red_packet_with_spl_token(ctx: Context<CreateRedPacketWithSPLToken>, total_number: u32, total_amount: u64, create_time: u64, duration: u64, open_time: u64, split_mode: SplitModeInput, pubkey_for_claim_signature: Pubkey, name: String, message: String, use_claim_receipts: bool, claim_authorization: ClaimAuthorization, randomness: RandomnessSource) -> Result<()> {
+        let split_mode = SplitMode::from(split_mode);
+        validate_create_params(total_number, total_amount, create_time, duration, open_time, &split_mode, use_claim_receipts, &claim_authorization)?;
+
+        fund_with_spl_token(&ctx.accounts.signer, &ctx.accounts.token_account, &ctx.accounts.vault, &ctx.accounts.token_mint, &ctx.accounts.token_program, total_amount)?;
+        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, RedPacketAddress::CreateTime, ctx.bumps.red_packet, total_number, total_amount, create_time, duration, open_time, AssetKind::SplToken, ctx.accounts.token_mint.key(), split_mode, use_claim_receipts, pubkey_for_claim_signature, claim_authorization, randomness, name, message);
+        fund_claim_records(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, total_number)?;
+        emit_cpi!(created_event(&ctx.accounts.red_packet));
+
+        Ok(())
+    }
+
+    pub fn create_red_packet_with_native_token(ctx: Context<CreateRedPacketWithNativeToken>, total_number: u32, total_amount: u64, create_time: u64, duration: u64, open_time: u64, split_mode: SplitModeInput, pubkey_for_claim_signature: Pubkey, name: String, message: String, use_claim_receipts: bool, claim_authorization: ClaimAuthorization, randomness: RandomnessSource) -> Result<()> {
+        let split_mode = SplitMode::from(split_mode);
+        validate_create_params(total_number, total_amount, create_time, duration, open_time, &split_mode, use_claim_receipts, &claim_authorization)?;
+
+        fund_with_native_token(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, total_amount)?;
+        initialize_red_packet(&mut ctx.accounts.red_packet, *ctx.accounts.signer.key, RedPacketAddress::CreateTime, ctx.bumps.red_packet, total_number, total_amount, create_time, duration, open_time, AssetKind::Native, Pubkey::default(), split_mode, use_claim_receipts, pubkey_for_claim_signature, claim_authorization, randomness, name, message);
+        fund_claim_records(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, total_number)?;
+        emit_cpi!(created_event(&ctx.accounts.red_packet));
+
+        Ok(())
+
+    }
+
+    pub fn top_up_red_packet(ctx: Context<TopUpRedPacket>, additional_number: u32, additional_amount: u64) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        require!(current_time < red_packet.create_time + red_packet.duration, CustomError::RedPacketExpired);
+        require!(additional_amount > 0, CustomError::InvalidTotalAmount);
+
+        let total_number = red_packet.total_number.checked_add(additional_number).ok_or(CustomError::InvalidTotalNumber)?;
+        let total_amount = red_packet.total_amount.checked_add(additional_amount).ok_or(CustomError::InvalidTotalAmount)?;
+        // claims recorded in the account stay capped at RED_PACKET_MAX_CLAIMERS
+        require!(red_packet.use_claim_receipts || total_number <= constants::RED_PACKET_MAX_CLAIMERS as u32, CustomError::InvalidTotalNumber);
+        let remaining_number = total_number - red_packet.claimed_number;
+        require!(remaining_number > 0, CustomError::InvalidTotalNumber);
+        split::validate_top_up(remaining_number, total_amount - red_packet.claimed_amount, &red_packet.split_mode)?;
+
+        match red_packet.token_type {
+            AssetKind::Native => {
+                require!(ctx.accounts.token_mint.is_none() && ctx.accounts.token_account.is_none() && ctx.accounts.vault.is_none(), CustomError::InvalidAccountForNativeToken);
+                fund_with_native_token(&ctx.accounts.signer, red_packet, &ctx.accounts.system_program, additional_amount)?;
+            }
+            AssetKind::SplToken => {
+                let (Some(token_mint), Some(token_account), Some(vault), Some(token_program)) = (&ctx.accounts.token_mint, &ctx.accounts.token_account, &ctx.accounts.vault, &ctx.accounts.token_program) else {
+                    return err!(CustomError::InvalidInitialParamsForTokenAccount);
+                };
+                require_keys_eq!(token_mint.key(), red_packet.token_address, CustomError::InvalidTokenType);
+                fund_with_spl_token(&ctx.accounts.signer, token_account, vault, token_mint, token_program, additional_amount)?;
+            }
+        }
+
+        fund_claim_records(&ctx.accounts.signer, &ctx.accounts.red_packet, &ctx.accounts.system_program, additional_number)?;
+
+        let red_packet = &mut ctx.accounts.red_packet;
+        red_packet.total_number = total_number;
+        red_packet.total_amount = total_amount;
+        emit_cpi!(RedPacketToppedUp {
+            red_packet: red_packet.key(),
+            additional_number,
+            additional_amount,
+            total_number,
+            total_amount,
+        });
 
         Ok(())
+    }
+
+    pub fn claim_red_packet(ctx: Context<ClaimRedPacket>, proof: ClaimProof) -> Result<()> {
+        let red_packet = &mut ctx.accounts.red_packet;
+        let (claim_amount, current_time) = prepare_claim(red_packet, &proof, ctx.accounts.claim_receipt.as_deref(), ctx.accounts.password_commit.as_deref(), ctx.accounts.random_commit.as_deref(), &ctx.accounts.instructions, &ctx.accounts.slot_hashes, ctx.accounts.signer.key, ctx.accounts.recipient.key)?;
+
+        // whether this claim pays out everything the packet still holds
+        let emptied = match red_packet.token_type {
+            AssetKind::Native => {
+                require!(ctx.accounts.token_mint.is_none() && ctx.accounts.recipient_token_account.is_none() && ctx.accounts.vault.is_none(), CustomError::InvalidAccountForNativeToken);
+                pay_out_native_token(&red_packet.to_account_info(), &ctx.accounts.recipient, claim_amount)?;
+                red_packet.claimed_amount + claim_amount == red_packet.total_amount
+            }
+            AssetKind::SplToken => {
+                let (Some(token_mint), Some(recipient_token_account), Some(vault), Some(token_program)) = (&ctx.accounts.token_mint, &ctx.accounts.recipient_token_account, &ctx.accounts.vault, &ctx.accounts.token_program) else {
+                    return err!(CustomError::InvalidInitialParamsForTokenAccount);
+                };
+                require_keys_eq!(token_mint.key(), red_packet.token_address, CustomError::InvalidTokenType);
+                pay_out_spl_token(red_packet, vault, recipient_token_account, token_mint, token_program, claim_amount)?;
+                // vault.amount is the balance before this transfer. Tokens sent to the vault by others keep it
+                // open, withdraw_red_packet returns them to the creator with the vault rent.
+                vault.amount == claim_amount
+            }
+        };
 
+        record_claim(red_packet, ctx.accounts.claim_receipt.as_mut(), ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount, current_time)?;
+        emit_cpi!(claimed_event(red_packet, ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount));
+
+        // the last claim closes the packet (and vault) and returns the rent to the creator
+        if let Some(creator) = &ctx.accounts.creator {
+            if emptied && red_packet.claimed_number == red_packet.total_number {
+                if let (Some(vault), Some(token_program)) = (&ctx.accounts.vault, &ctx.accounts.token_program) {
+                    close_vault(red_packet, vault, token_program, creator)?;
+                }
+                close_red_packet(&red_packet.to_account_info(), creator)?;
+                emit_cpi!(RedPacketClosed {
+                    red_packet: red_packet.key(),
+                    creator: creator.key(),
+                });
+            }
+        }
+
+        Ok(())
     }
     
-    pub fn claim_with_spl_token(ctx: Context<RedPacketWithSPLToken>) -> Result<()> {
+    pub fn claim_with_spl_token(ctx: Context<ClaimRedPacketWithSPLToken>, proof: ClaimProof) -> Result<()> {
         let red_packet = &mut ctx.accounts.red_packet;
-        let _current_time = Clock::get().unwrap().unix_timestamp;
-        let expiry = red_packet.create_time + red_packet.duration;
-        require!(_current_time < expiry as i64, CustomError::RedPacketExpired);
-        
-        require!(red_packet.claimed_number < red_packet.total_number, CustomError::RedPacketAllClaimed);
-        require!(!red_packet.claimed_users.contains(&ctx.accounts.signer.key()), CustomError::RedPacketClaimed);
-        
-        // verify signature
-        require!(verify_claim_signature(&ctx.accounts.instructions, red_packet.key().as_ref(), ctx.accounts.signer.key.as_ref(), red_packet.pubkey_for_claim_signature.to_bytes().as_ref()).is_ok(), CustomError::InvalidSignature);
-        
-        let claim_amount = calculate_claim_amount(&red_packet, ctx.accounts.signer.key());
+        require!(red_packet.token_type == AssetKind::SplToken, CustomError::InvalidTokenType);
+        // the vault constraint only ties the vault to the mint passed in
+        require_keys_eq!(ctx.accounts.token_mint.key(), red_packet.token_address, CustomError::InvalidTokenType);
+        let (claim_amount, current_time) = prepare_claim(red_packet, &proof, ctx.accounts.claim_receipt.as_deref(), ctx.accounts.password_commit.as_deref(), ctx.accounts.random_commit.as_deref(), &ctx.accounts.instructions, &ctx.accounts.slot_hashes, ctx.accounts.signer.key, ctx.accounts.recipient.key)?;
 
-        // check if the claim amount is valid
-        require!(red_packet.claimed_amount + claim_amount <= red_packet.total_amount, CustomError::InvalidClaimAmount);
+        pay_out_spl_token(red_packet, &ctx.accounts.vault, &ctx.accounts.recipient_token_account, &ctx.accounts.token_mint, &ctx.accounts.token_program, claim_amount)?;
         
-        // Transfer SPL tokens from vault to claimer's token account
-        // Signer seeds for PDA authority
-        let binding = red_packet.creator.key();
-        let binding_time = red_packet.create_time.to_le_bytes();
-        let seeds = &[binding.as_ref(), binding_time.as_ref(), &[ctx.bumps.red_packet]];
-        let signer_seeds = &[&seeds[..]];
-        transfer::transfer_tokens(
-            &ctx.accounts.vault,
-            &ctx.accounts.token_account,
-            &claim_amount,
-            &ctx.accounts.token_mint,
-            &red_packet.to_account_info(),
-            &ctx.accounts.token_program,
-            signer_seeds
-        )?;
-        
-        red_packet.claimed_users.push(ctx.accounts.signer.key());
-        red_packet.claimed_amount_records.push(claim_amount);
-        red_packet.claimed_number += 1;
-        red_packet.claimed_amount += claim_amount;
+        record_claim(red_packet, ctx.accounts.claim_receipt.as_mut(), ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount, current_time)?;
+        emit_cpi!(claimed_event(red_packet, ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount));
         
         Ok(())
     }
 
-    pub fn claim_with_native_token(ctx: Context<RedPacketWithNativeToken>) -> Result<()> {
+    pub fn claim_with_native_token(ctx: Context<ClaimRedPacketWithNativeToken>, proof: ClaimProof) -> Result<()> {
         let red_packet = &mut ctx.accounts.red_packet;
-
-        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
-        let expiry = red_packet.create_time + red_packet.duration;
-        require!(current_time < expiry, CustomError::RedPacketExpired);
-        require!(red_packet.claimed_number < red_packet.total_number, CustomError::RedPacketAllClaimed);
-        require!(!red_packet.claimed_users.contains(&ctx.accounts.signer.key()), CustomError::RedPacketClaimed);
-
-        // verify signature
-        require!(verify_claim_signature(&ctx.accounts.instructions, red_packet.key().as_ref(), ctx.accounts.signer.key.as_ref(), red_packet.pubkey_for_claim_signature.to_bytes().as_ref()).is_ok(), CustomError::InvalidSignature);
-        let claim_amount = calculate_claim_amount(&red_packet, ctx.accounts.signer.key());
-        
-        // check if the claim amount is valid
-        require!(red_packet.claimed_amount + claim_amount <= red_packet.total_amount, CustomError::InvalidClaimAmount);
+        require!(red_packet.token_type == AssetKind::Native, CustomError::InvalidTokenType);
+        let (claim_amount, current_time) = prepare_claim(red_packet, &proof, ctx.accounts.claim_receipt.as_deref(), ctx.accounts.password_commit.as_deref(), ctx.accounts.random_commit.as_deref(), &ctx.accounts.instructions, &ctx.accounts.slot_hashes, ctx.accounts.signer.key, ctx.accounts.recipient.key)?;
        
-        // Transfer SOL using native transfer
-        **red_packet.to_account_info().try_borrow_mut_lamports()? -= claim_amount;
-        **ctx.accounts.signer.to_account_info().try_borrow_mut_lamports()? += claim_amount;
+        pay_out_native_token(&red_packet.to_account_info(), &ctx.accounts.recipient, claim_amount)?;
                
-        red_packet.claimed_users.push(ctx.accounts.signer.key());
-        red_packet.claimed_amount_records.push(claim_amount);
-        red_packet.claimed_number += 1;
-        red_packet.claimed_amount += claim_amount;
+        record_claim(red_packet, ctx.accounts.claim_receipt.as_mut(), ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount, current_time)?;
+        emit_cpi!(claimed_event(red_packet, ctx.accounts.signer.key(), ctx.accounts.recipient.key(), claim_amount));
 
         Ok(())
     }
 
-    pub fn withdraw_with_spl_token(ctx: Context<RedPacketWithSPLToken>) -> Result<()> {
+    pub fn commit_password_claim(ctx: Context<CommitPasswordClaim>, commitment: [u8; 32]) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        require!(matches!(red_packet.claim_authorization, ClaimAuthorization::Password { .. }), CustomError::InvalidClaimProof);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        require!(current_time < red_packet.create_time + red_packet.duration, CustomError::RedPacketExpired);
+
+        // committing again overwrites the previous commitment and restarts the wait
+        ctx.accounts.password_commit.set_inner(PasswordCommit {
+            commitment,
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    // First step of a claim on a red packet drawing shares from RandomnessSource::SlotHashes: the
+    // share comes from the hash of the slot this lands in, which nobody knows yet. The claim has to
+    // follow within RANDOM_CLAIM_WINDOW_SLOTS, so the claimer cannot wait for other claims to move
+    // the share, and a commit that missed it can only be made again once close_random_commit allows.
+    pub fn commit_random_claim(ctx: Context<CommitRandomClaim>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        require!(red_packet.needs_random_commit(), CustomError::InvalidRandomSeed);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        require!(current_time < red_packet.create_time + red_packet.duration, CustomError::RedPacketExpired);
+        require!(current_time >= red_packet.open_time, CustomError::RedPacketNotOpen);
+        // the seed must be final before any draw, see reveal_random_seed
+        require!(red_packet.random_seed.is_some() || current_time >= red_packet.random_seed_reveal_deadline(), CustomError::RandomSeedNotRevealed);
+
+        ctx.accounts.random_commit.set_inner(RandomCommit {
+            slot: Clock::get()?.slot,
+        });
+
+        Ok(())
+    }
+
+    // Frees a commit whose claim window has passed, so its claimer can commit again. That only
+    // works once the commit slot's hash has left the SlotHashes sysvar, which keeps claimers from
+    // re-drawing right after seeing a bad slot hash. The red packet may be closed already.
+    pub fn close_random_commit(ctx: Context<CloseRandomCommit>) -> Result<()> {
+        let commit_slot = ctx.accounts.random_commit.slot;
+        require!(Clock::get()?.slot > commit_slot + solana_program::slot_hashes::MAX_ENTRIES as u64, CustomError::RandomCommitNotExpired);
+
+        Ok(())
+    }
+
+    // Past random_seed_reveal_deadline the packet draws from slot hashes alone, so a creator who
+    // never reveals cannot block claims, and a late reveal could not change draws already committed.
+    pub fn reveal_random_seed(ctx: Context<RevealRandomSeed>, seed: [u8; 32]) -> Result<()> {
         let red_packet = &mut ctx.accounts.red_packet;
-        let _current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
-        let expiry = red_packet.create_time + red_packet.duration;
-        require!(_current_time >= expiry, CustomError::RedPacketNotExpired);
         require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        match red_packet.randomness {
+            RandomnessSource::SlotHashes { seed_commitment } => {
+                require!(red_packet.random_seed.is_none(), CustomError::InvalidRandomSeed);
+                require!(randomness::seed_commitment(&seed) == seed_commitment, CustomError::InvalidRandomSeed);
+            }
+            RandomnessSource::Timestamp => return err!(CustomError::InvalidRandomSeed),
+        }
+        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        require!(current_time < red_packet.random_seed_reveal_deadline(), CustomError::RandomSeedRevealExpired);
+        red_packet.random_seed = Some(seed);
 
-        let remaining_amount = red_packet.total_amount - red_packet.claimed_amount;
-       
-        // Transfer SPL tokens from vault to creator's token account
-        // Signer seeds for PDA authority
-        let binding = red_packet.creator.key();
-        let binding_time = red_packet.create_time.to_le_bytes();
-        let seeds = &[binding.as_ref(), binding_time.as_ref(), &[ctx.bumps.red_packet]];
-        let signer_seeds = &[&seeds[..]];
-        transfer::transfer_tokens(
-            &ctx.accounts.vault,
-            &ctx.accounts.token_account,
-            &remaining_amount,
-            &ctx.accounts.token_mint,
-            &red_packet.to_account_info(),
-            &ctx.accounts.token_program,
-            signer_seeds
-        )?;
-
-        let accounts = CloseAccount {
-            account: ctx.accounts.vault.to_account_info(),
-            destination: ctx.accounts.signer.to_account_info(),
-            authority: ctx.accounts.red_packet.to_account_info(),
-        };
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            accounts,
-            signer_seeds,
-        );
-        close_account(cpi_context)?;
+        Ok(())
+    }
 
-        // Transfer all redpacket lamports (remaining balance + rent) to signer
-        let dest_starting_lamports = ctx.accounts.signer.lamports();
-        let red_packet_lamports = ctx.accounts.red_packet.to_account_info().lamports();
-        **ctx.accounts.red_packet.to_account_info().try_borrow_mut_lamports()? = 0;
-        **ctx.accounts.signer.to_account_info().try_borrow_mut_lamports()? = dest_starting_lamports
-            .checked_add(red_packet_lamports)
-            .unwrap();
+    pub fn update_red_packet_expiry(ctx: Context<UpdateRedPacketExpiry>, new_expiry: u64) -> Result<()> {
+        let red_packet = &mut ctx.accounts.red_packet;
+        require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+        let old_expiry = red_packet.create_time + red_packet.duration;
+        require!(current_time < old_expiry, CustomError::RedPacketExpired);
+        require!(new_expiry > current_time && new_expiry > red_packet.create_time.max(red_packet.open_time), CustomError::InvalidExpiryTime);
+        // claimers may have relied on the announced deadline, so it only moves forward once someone claimed
+        require!(new_expiry >= old_expiry || red_packet.claimed_number == 0, CustomError::InvalidExpiryTime);
+
+        red_packet.duration = new_expiry - red_packet.create_time;
+        emit_cpi!(RedPacketExpiryUpdated {
+            red_packet: red_packet.key(),
+            old_expiry,
+            new_expiry,
+        });
 
         Ok(())
     }
 
-    pub fn withdraw_with_native_token(ctx: Context<RedPacketWithNativeToken>) -> Result<()> {
+    // Rotates pubkey_for_claim_signature, e.g. after the signing key leaked, which also voids every
+    // signature made by the old key. None pauses all claims until a key is set again. Only Signature
+    // and SignatureV1 packets check that key; the others rotate with update_claim_authorization.
+    pub fn update_claim_signer(ctx: Context<UpdateClaimSigner>, new_signer: Option<Pubkey>) -> Result<()> {
         let red_packet = &mut ctx.accounts.red_packet;
-        let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
-        let expiry = red_packet.create_time + red_packet.duration;
-        require!(current_time >= expiry, CustomError::RedPacketNotExpired);
         require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+
+        let old_signer = red_packet.pubkey_for_claim_signature;
+        match new_signer {
+            Some(new_signer) => {
+                require!(red_packet.claim_authorization.uses_claim_signer(), CustomError::ClaimSignerNotUsed);
+                red_packet.pubkey_for_claim_signature = new_signer;
+                red_packet.claims_paused = false;
+            }
+            None => red_packet.claims_paused = true,
+        }
+        emit_cpi!(RedPacketClaimSignerUpdated {
+            red_packet: red_packet.key(),
+            old_signer,
+            new_signer: red_packet.pubkey_for_claim_signature,
+            claims_paused: red_packet.claims_paused,
+        });
+
+        Ok(())
+    }
+
+    // Replaces the claim authorization with one of the same kind, e.g. new MultiSignature signers
+    // and threshold or a new Secp256k1 eth_address, voiding approvals by the old ones. Like
+    // update_claim_signer with a key, this also resumes paused claims.
+    pub fn update_claim_authorization(ctx: Context<UpdateClaimSigner>, claim_authorization: ClaimAuthorization) -> Result<()> {
+        let red_packet = &mut ctx.accounts.red_packet;
+        require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+        require!(std::mem::discriminant(&claim_authorization) == std::mem::discriminant(&red_packet.claim_authorization), CustomError::InvalidClaimAuthorization);
+        validate_claim_authorization(&claim_authorization)?;
+
+        let old_authorization = std::mem::replace(&mut red_packet.claim_authorization, claim_authorization);
+        red_packet.claims_paused = false;
+        emit_cpi!(RedPacketClaimAuthorizationUpdated {
+            red_packet: red_packet.key(),
+            old_authorization,
+            new_authorization: red_packet.claim_authorization.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_red_packet(ctx: Context<WithdrawRedPacket>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        check_withdrawable(red_packet, ctx.accounts.signer.key)?;
+
+        // a cancelled packet was already refunded and its vault closed by cancel_red_packet
+        let refunded_amount = if red_packet.cancelled {
+            0
+        } else {
+            refund_remainder(red_packet, &ctx.accounts.signer, ctx.accounts.token_mint.as_ref(), ctx.accounts.token_account.as_ref(), ctx.accounts.vault.as_ref(), ctx.accounts.token_program.as_ref())?
+        };
+        emit_cpi!(withdrawn_event(red_packet, refunded_amount));
+
+        close_red_packet(&ctx.accounts.red_packet.to_account_info(), &ctx.accounts.signer)
+    }
+
+    // Stops a live red packet early: the unclaimed remainder goes back to the creator and the
+    // vault is closed. The packet account is kept so later claims fail with RedPacketCancelled;
+    // its rent is reclaimed with withdraw_red_packet.
+    pub fn cancel_red_packet(ctx: Context<WithdrawRedPacket>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        require!(red_packet.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+        require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+
+        let refunded_amount = refund_remainder(red_packet, &ctx.accounts.signer, ctx.accounts.token_mint.as_ref(), ctx.accounts.token_account.as_ref(), ctx.accounts.vault.as_ref(), ctx.accounts.token_program.as_ref())?;
+        emit_cpi!(RedPacketCancelled {
+            red_packet: red_packet.key(),
+            creator: red_packet.creator,
+            refunded_amount,
+        });
+        ctx.accounts.red_packet.cancelled = true;
+
+        Ok(())
+    }
+
+    // Returns a claim receipt's rent to its claimer. Receipts block double claims, so they can only
+    // go once the red packet account is closed by withdraw_red_packet or the last claim, or a new
+    // red packet was created at its address (create_slot tells them apart).
+    pub fn close_claim_receipt(ctx: Context<CloseClaimReceipt>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        let closed = red_packet.lamports() == 0 || *red_packet.owner != crate::ID;
+        let replaced = !closed && RedPacket::try_deserialize(&mut &red_packet.try_borrow_data()?[..])
+            .is_ok_and(|current| current.create_slot != ctx.accounts.claim_receipt.red_packet_create_slot);
+        require!(closed || replaced, CustomError::RedPacketNotClosed);
+
+        Ok(())
+    }
+
+    // Refunds and closes a red packet created by the first deployed version of the program, whose
+    // account layout the other instructions cannot read. Nobody can claim these packets any more,
+    // so their creator may close them right away instead of waiting for the expiry.
+    pub fn withdraw_legacy_red_packet(ctx: Context<WithdrawLegacyRedPacket>) -> Result<()> {
+        let legacy = legacy::LegacyRedPacket::decode(&ctx.accounts.red_packet.try_borrow_data()?)?;
+        require!(legacy.creator == *ctx.accounts.signer.key, CustomError::Unauthorized);
+
+        let create_time = legacy.create_time.to_le_bytes();
+        let (address, bump) = Pubkey::find_program_address(&[legacy.creator.as_ref(), create_time.as_ref()], &crate::ID);
+        require_keys_eq!(address, ctx.accounts.red_packet.key(), CustomError::NotLegacyRedPacket);
+
+        let accounts = &ctx.accounts;
+        if legacy.token_type == legacy::TOKEN_TYPE_SPL {
+            let (Some(token_mint), Some(token_account), Some(vault), Some(token_program)) = (&accounts.token_mint, &accounts.token_account, &accounts.vault, &accounts.token_program) else {
+                return err!(CustomError::InvalidInitialParamsForTokenAccount);
+            };
+            require_keys_eq!(token_mint.key(), legacy.token_address, CustomError::InvalidTokenType);
+            let seeds = &[legacy.creator.as_ref(), create_time.as_ref(), &[bump]];
+            drain_and_close_vault(&accounts.red_packet.to_account_info(), &[&seeds[..]], vault, token_account, token_mint, token_program, &accounts.signer)?;
+        } else {
+            require!(accounts.token_mint.is_none() && accounts.token_account.is_none() && accounts.vault.is_none(), CustomError::InvalidAccountForNativeToken);
+        }
+
+        close_red_packet(&accounts.red_packet.to_account_info(), &accounts.signer)
+    }
+
+    pub fn withdraw_with_spl_token(ctx: Context<RedPacketWithSPLToken>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        check_withdrawable(red_packet, ctx.accounts.signer.key)?;
+        require!(red_packet.token_type == AssetKind::SplToken, CustomError::InvalidTokenType);
+        require_keys_eq!(ctx.accounts.token_mint.key(), red_packet.token_address, CustomError::InvalidTokenType);
+
+        // cancel_red_packet closed the vault, so for a cancelled packet this one was re-created since;
+        // it is still emptied and closed so its rent is not left behind
+        let refunded_amount = empty_and_close_vault(red_packet, &ctx.accounts.vault, &ctx.accounts.token_account, &ctx.accounts.token_mint, &ctx.accounts.token_program, &ctx.accounts.signer)?;
+        emit_cpi!(withdrawn_event(red_packet, refunded_amount));
+
+        // Transfer all redpacket lamports (remaining balance + rent) to signer
+        close_red_packet(&ctx.accounts.red_packet.to_account_info(), &ctx.accounts.signer)
+    }
+
+    pub fn withdraw_with_native_token(ctx: Context<RedPacketWithNativeToken>) -> Result<()> {
+        let red_packet = &ctx.accounts.red_packet;
+        check_withdrawable(red_packet, ctx.accounts.signer.key)?;
+        require!(red_packet.token_type == AssetKind::Native, CustomError::InvalidTokenType);
+        emit_cpi!(withdrawn_event(red_packet, unclaimed_amount(red_packet)));
       
         // Transfer all lamports (remaining balance + rent) to signer
-        let dest_starting_lamports = ctx.accounts.signer.lamports();
-        let red_packet_lamports = ctx.accounts.red_packet.to_account_info().lamports();
-        **ctx.accounts.red_packet.to_account_info().try_borrow_mut_lamports()? = 0;
-        **ctx.accounts.signer.to_account_info().try_borrow_mut_lamports()? = dest_starting_lamports
-            .checked_add(red_packet_lamports)
-            .unwrap();
-      
-        Ok(())
+        close_red_packet(&ctx.accounts.red_packet.to_account_info(), &ctx.accounts.signer)
     }
 
 }
 
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(total_number: u8, total_amount: u64, create_time: u64)] 
-pub struct CreateRedPacketWithSPLToken<'info> {
+#[instruction(asset_kind: AssetKind, total_number: u32, total_amount: u64, create_time: u64)] 
+pub struct CreateRedPacket<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     
     #[account(
         init, 
         payer = signer, 
-        space = 8 + RedPacket::INIT_SPACE, 
+        space = RedPacket::space(0), 
         seeds = [signer.key().as_ref(), create_time.to_le_bytes().as_ref()], 
         bump
     )]
     pub red_packet: Account<'info, RedPacket>,
 
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    // token accounts are only passed for AssetKind::SplToken
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
@@ -242,7 +465,7 @@ pub struct CreateRedPacketWithSPLToken<'info> {
         associated_token::authority = signer,
         associated_token::token_program = token_program
     )]
-    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     #[account(
         init,
@@ -251,32 +474,139 @@ pub struct CreateRedPacketWithSPLToken<'info> {
         associated_token::authority = red_packet,
         associated_token::token_program = token_program,
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Interface<'info, TokenInterface>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(id: [u8; 32])]
+pub struct CreateRedPacketWithId<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = RedPacket::space(0),
+        seeds = [signer.key().as_ref(), id.as_ref()],
+        bump
+    )]
+    pub red_packet: Account<'info, RedPacket>,
+
+    // token accounts are only passed for AssetKind::SplToken
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TopUpRedPacket<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    // token accounts are only passed for AssetKind::SplToken
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct RedPacketWithSPLToken<'info> {
+pub struct ClaimRedPacket<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
-    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.create_time.to_le_bytes().as_ref()], bump)]
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
     pub red_packet: Account<'info, RedPacket>,
-  
-    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: any wallet chosen by the claimer, bound into the claim signature message
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: the red packet creator, only needed to close a fully claimed packet on the last claim
+    #[account(mut, address = red_packet.creator)]
+    pub creator: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + ClaimReceipt::INIT_SPACE,
+        seeds = [CLAIM_RECEIPT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Option<Account<'info, ClaimReceipt>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [PASSWORD_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub password_commit: Option<Account<'info, PasswordCommit>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [RANDOM_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub random_commit: Option<Account<'info, RandomCommit>>,
+
+    // token accounts are only passed for AssetKind::SplToken
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
 
     #[account(
         init_if_needed, 
         payer = signer,
         associated_token::mint = token_mint,
-        associated_token::authority = signer,
+        associated_token::authority = recipient,
         associated_token::token_program = token_program
     )]
-    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     #[account(
         mut,
@@ -284,10 +614,10 @@ pub struct RedPacketWithSPLToken<'info> {
         associated_token::authority = red_packet,
         associated_token::token_program = token_program,
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Interface<'info, TokenInterface>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
     /// CHECK: follow the code 
     /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
@@ -297,180 +627,1396 @@ pub struct RedPacketWithSPLToken<'info> {
     /// CHECK: Ed25519Program ID is checked in constraint
     #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
     pub ed25519_program: UncheckedAccount<'info>,
+    /// CHECK: SlotHashes sysvar ID is checked in constraint, read by randomness::slot_hash
+    #[account(address = solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
 }
 
+// Also used by cancel_red_packet
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(total_number: u8, total_amount: u64, create_time: u64)] 
-pub struct CreateRedPacketWithNativeToken<'info> {
+pub struct WithdrawRedPacket<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
-    #[account(init, payer = signer, space = 8 + RedPacket::INIT_SPACE, seeds = [signer.key().as_ref(), create_time.to_le_bytes().as_ref()], bump)]
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
     pub red_packet: Account<'info, RedPacket>,
 
+    // token accounts are only passed for AssetKind::SplToken
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed, 
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RedPacketWithNativeToken<'info> {
+pub struct WithdrawLegacyRedPacket<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
-    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.create_time.to_le_bytes().as_ref()], bump)]
-    pub red_packet: Account<'info, RedPacket>,
+    /// CHECK: decoded by legacy::LegacyRedPacket::decode, address checked against its [creator, create_time] seeds
+    #[account(mut, owner = crate::ID)]
+    pub red_packet: UncheckedAccount<'info>,
+
+    // token accounts are only passed for legacy SPL token red packets
+    pub token_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
-    /// CHECK: follow the code 
-    /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
-    /// https://solana.stackexchange.com/questions/16487/about-verify-signature-with-ed25519-issue?rq=1
-    #[account(address = solana_program::sysvar::instructions::ID)]
-    pub instructions: AccountInfo<'info>,
-    /// CHECK: Ed25519Program ID is checked in constraint
-    #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
-    pub ed25519_program: UncheckedAccount<'info>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(total_number: u32, total_amount: u64, create_time: u64)] 
+pub struct CreateRedPacketWithSPLToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    #[account(
+        init, 
+        payer = signer, 
+        space = RedPacket::space(0), 
+        seeds = [signer.key().as_ref(), create_time.to_le_bytes().as_ref()], 
+        bump
+    )]
+    pub red_packet: Account<'info, RedPacket>,
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
-struct Ed25519SignatureOffsets {
-    signature_offset: u16,             // offset to ed25519 signature of 64 bytes
-    signature_instruction_index: u16,  // instruction index to find signature
-    public_key_offset: u16,            // offset to public key of 32 bytes
-    public_key_instruction_index: u16, // instruction index to find public key
-    message_data_offset: u16,          // offset to start of message data
-    message_data_size: u16,            // size of message data
-    message_instruction_index: u16,    // index of instruction data to get message data
-}
+    pub token_mint: InterfaceAccount<'info, Mint>,
 
-#[account]
-#[derive(InitSpace)]
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RedPacketWithSPLToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+  
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed, 
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: follow the code 
+    /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
+    /// https://solana.stackexchange.com/questions/16487/about-verify-signature-with-ed25519-issue?rq=1
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    /// CHECK: Ed25519Program ID is checked in constraint
+    #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
+    pub ed25519_program: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimRedPacketWithSPLToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+  
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: any wallet chosen by the claimer, bound into the claim signature message
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + ClaimReceipt::INIT_SPACE,
+        seeds = [CLAIM_RECEIPT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Option<Account<'info, ClaimReceipt>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [PASSWORD_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub password_commit: Option<Account<'info, PasswordCommit>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [RANDOM_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub random_commit: Option<Account<'info, RandomCommit>>,
+
+    #[account(
+        init_if_needed, 
+        payer = signer,
+        associated_token::mint = token_mint,
+        associated_token::authority = recipient,
+        associated_token::token_program = token_program
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = red_packet,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: follow the code 
+    /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
+    /// https://solana.stackexchange.com/questions/16487/about-verify-signature-with-ed25519-issue?rq=1
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    /// CHECK: Ed25519Program ID is checked in constraint
+    #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
+    pub ed25519_program: UncheckedAccount<'info>,
+    /// CHECK: SlotHashes sysvar ID is checked in constraint, read by randomness::slot_hash
+    #[account(address = solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(total_number: u32, total_amount: u64, create_time: u64)] 
+pub struct CreateRedPacketWithNativeToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(init, payer = signer, space = RedPacket::space(0), seeds = [signer.key().as_ref(), create_time.to_le_bytes().as_ref()], bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RedPacketWithNativeToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    pub system_program: Program<'info, System>,
+    /// CHECK: follow the code 
+    /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
+    /// https://solana.stackexchange.com/questions/16487/about-verify-signature-with-ed25519-issue?rq=1
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    /// CHECK: Ed25519Program ID is checked in constraint
+    #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
+    pub ed25519_program: UncheckedAccount<'info>,
+}
+
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimRedPacketWithNativeToken<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    /// CHECK: any wallet chosen by the claimer, bound into the claim signature message
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + ClaimReceipt::INIT_SPACE,
+        seeds = [CLAIM_RECEIPT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Option<Account<'info, ClaimReceipt>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [PASSWORD_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub password_commit: Option<Account<'info, PasswordCommit>>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [RANDOM_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub random_commit: Option<Account<'info, RandomCommit>>,
+
+    pub system_program: Program<'info, System>,
+    /// CHECK: follow the code 
+    /// https://github.com/GuidoDipietro/solana-ed25519-secp256k1-sig-verification/blob/master/programs/solana-ed25519-sig-verification/src/lib.rs
+    /// https://solana.stackexchange.com/questions/16487/about-verify-signature-with-ed25519-issue?rq=1
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    /// CHECK: Ed25519Program ID is checked in constraint
+    #[account(address = anchor_lang::solana_program::ed25519_program::ID)]
+    pub ed25519_program: UncheckedAccount<'info>,
+    /// CHECK: SlotHashes sysvar ID is checked in constraint, read by randomness::slot_hash
+    #[account(address = solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitPasswordClaim<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + PasswordCommit::INIT_SPACE,
+        seeds = [PASSWORD_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub password_commit: Account<'info, PasswordCommit>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseClaimReceipt<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// CHECK: the receipt's red packet, which must be closed or replaced by a new one, see close_claim_receipt
+    #[account(address = claim_receipt.red_packet)]
+    pub red_packet: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [CLAIM_RECEIPT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub claim_receipt: Account<'info, ClaimReceipt>,
+}
+
+#[derive(Accounts)]
+pub struct CommitRandomClaim<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = 8 + RandomCommit::INIT_SPACE,
+        seeds = [RANDOM_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub random_commit: Account<'info, RandomCommit>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRandomCommit<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// CHECK: only part of the commit's seeds, the red packet may have been closed since
+    pub red_packet: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = signer,
+        seeds = [RANDOM_COMMIT_SEED, red_packet.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub random_commit: Account<'info, RandomCommit>,
+}
+
+#[derive(Accounts)]
+pub struct RevealRandomSeed<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateRedPacketExpiry<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateClaimSigner<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut, seeds = [red_packet.creator.key().as_ref(), red_packet.address_seed().as_ref()], bump = red_packet.bump)]
+    pub red_packet: Account<'info, RedPacket>,
+}
+
+#[account]
+#[derive(InitSpace)]
 pub struct RedPacket {
     pub creator: Pubkey,
-    pub total_number: u8,
-    pub claimed_number: u8,
+    pub address: RedPacketAddress, // which seed follows the creator key in the PDA seeds
+    pub bump: u8,
+    pub total_number: u32,
+    pub claimed_number: u32,
     pub total_amount: u64,
     pub claimed_amount: u64,
     pub create_time: u64,
+    pub create_slot: u64, // tells apart red packets created at the same address, recorded in claim receipts
     pub duration: u64,
-    pub token_type: u8, // 0: SOL, 1: SPL Token
+    pub open_time: u64, // claims open at this time, the expiry is still create_time + duration
+    pub token_type: AssetKind,
     pub token_address: Pubkey,
-    pub if_spilt_random: bool,
-    #[max_len(200)]
+    pub split_mode: SplitMode,
+    pub use_claim_receipts: bool, // claims are recorded in ClaimReceipt PDAs instead of the vectors below
+    #[max_len(RED_PACKET_MAX_CLAIMERS)]
     pub claimed_users: Vec<Pubkey>, // Record of claimers
-    #[max_len(200)]
+    #[max_len(RED_PACKET_MAX_CLAIMERS)]
+    pub claimed_recipients: Vec<Pubkey>, // Record of accounts that received each claim
+    #[max_len(RED_PACKET_MAX_CLAIMERS)]
     pub claimed_amount_records: Vec<u64>, // Record of claimers' amount
     pub pubkey_for_claim_signature: Pubkey, // Record of claimers' pubkey and claim amount
+    pub claim_authorization: ClaimAuthorization,
+    pub randomness: RandomnessSource,
+    pub random_seed: Option<[u8; 32]>, // revealed by the creator for RandomnessSource::SlotHashes, all zeros if never revealed
+    pub cancelled: bool, // set by cancel_red_packet, the unclaimed remainder has been refunded
+    pub claims_paused: bool, // set by update_claim_signer with no signer, cleared when a signer is set again
     #[max_len(100)]
     pub name: String,
     #[max_len(200)]
     pub message: String,
 }
 
+// seeds = [creator, create_time.to_le_bytes()] for packets made by the create_time based
+// instructions, [creator, id] for create_red_packet_with_id
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum RedPacketAddress {
+    CreateTime,
+    Id { id: [u8; 32] },
+}
+
+// Serialized as a single byte, so token_type keeps the 0: SOL, 1: SPL Token layout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AssetKind {
+    Native,
+    SplToken,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum SplitMode {
+    Equal,
+    Random { split: RandomSplit },
+    // explicit amounts, handed out in tier order or, if shuffled, in random order
+    FixedList {
+        #[max_len(RED_PACKET_MAX_SHARE_TIERS)]
+        tiers: Vec<ShareTier>,
+        shuffled: bool,
+    },
+    // like FixedList, but each share is weight_bps / SPLIT_WEIGHT_TOTAL_BPS of total_amount
+    WeightedTiers {
+        #[max_len(RED_PACKET_MAX_SHARE_TIERS)]
+        tiers: Vec<WeightTier>,
+        shuffled: bool,
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ShareTier {
+    pub count: u32,
+    pub amount: u64,
+    pub claimed: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct WeightTier {
+    pub count: u32,
+    pub weight_bps: u64,
+    pub claimed: u32,
+}
+
+// The split_mode argument of the create instructions: SplitMode without the tiers' claimed
+// counters, which the program keeps and starts at zero
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum SplitModeInput {
+    Equal,
+    Random { split: RandomSplit },
+    FixedList { tiers: Vec<ShareTierInput>, shuffled: bool },
+    WeightedTiers { tiers: Vec<WeightTierInput>, shuffled: bool },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ShareTierInput {
+    pub count: u32,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct WeightTierInput {
+    pub count: u32,
+    pub weight_bps: u64,
+}
+
+impl From<SplitModeInput> for SplitMode {
+    fn from(split_mode: SplitModeInput) -> Self {
+        match split_mode {
+            SplitModeInput::Equal => SplitMode::Equal,
+            SplitModeInput::Random { split } => SplitMode::Random { split },
+            SplitModeInput::FixedList { tiers, shuffled } => SplitMode::FixedList {
+                tiers: tiers.into_iter().map(|tier| ShareTier { count: tier.count, amount: tier.amount, claimed: 0 }).collect(),
+                shuffled,
+            },
+            SplitModeInput::WeightedTiers { tiers, shuffled } => SplitMode::WeightedTiers {
+                tiers: tiers.into_iter().map(|tier| WeightTier { count: tier.count, weight_bps: tier.weight_bps, claimed: 0 }).collect(),
+                shuffled,
+            },
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum RandomSplit {
+    Legacy, // random % (remaining * 2 / left), at least 1
+    DoubleAverage { min_share: u64, max_share: u64 }, // max_share == 0 means no cap, see split::double_average_share
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum ClaimAuthorization {
+    Signature, // Ed25519 signature from pubkey_for_claim_signature in the preceding instruction
+    SignatureV1, // like Signature, over claim_message::claim_message_v1 with a deadline and an optional max amount
+    // leaves are merkle::allowlist_leaf(claimer, amount); a non-zero amount is paid as is, which only
+    // Equal and legacy Random splits accept (split::accepts_fixed_amounts)
+    MerkleAllowlist { root: [u8; 32] },
+    Password { hash: [u8; 32] }, // password::password_hash(red_packet, phrase), revealed after commit_password_claim
+    // at least threshold distinct signers sign the claim message in one multi-signature Ed25519 instruction,
+    // the legacy message or claim_message_v1 when the claim carries ClaimProof::SignatureV1
+    MultiSignature {
+        #[max_len(RED_PACKET_MAX_CLAIM_SIGNERS)]
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    },
+    // secp256k1 signature recovering to this Ethereum address in the preceding Secp256k1 instruction, over
+    // the legacy message or claim_message_v1 when the claim carries ClaimProof::SignatureV1, either raw or
+    // with the personal_sign prefix (secp256k1::personal_sign_message) EVM wallets sign with
+    Secp256k1 { eth_address: [u8; 20] },
+}
+
+impl ClaimAuthorization {
+    // Whether claims are checked against pubkey_for_claim_signature
+    pub fn uses_claim_signer(&self) -> bool {
+        matches!(self, ClaimAuthorization::Signature | ClaimAuthorization::SignatureV1)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum RandomnessSource {
+    Timestamp, // hash of red packet, claimer and unix timestamp, predictable by the claimer
+    SlotHashes { seed_commitment: [u8; 32] }, // randomness::seed_commitment(seed), seed revealed via reveal_random_seed
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum ClaimProof {
+    None,
+    Merkle { proof: Vec<[u8; 32]>, amount: u64 },
+    Password { phrase: String },
+    // the deadline and max amount covered by a ClaimAuthorization::SignatureV1 signature
+    SignatureV1 { deadline: u64, max_amount: u64 },
+}
+
+impl RedPacket {
+    // PDA seed after the creator key, see RedPacketAddress
+    pub fn address_seed(&self) -> Vec<u8> {
+        match self.address {
+            RedPacketAddress::CreateTime => self.create_time.to_le_bytes().to_vec(),
+            RedPacketAddress::Id { id } => id.to_vec(),
+        }
+    }
+
+    // Claims on packets drawing random shares from slot hashes go through commit_random_claim
+    pub fn needs_random_commit(&self) -> bool {
+        let random_split = match &self.split_mode {
+            SplitMode::Equal => false,
+            SplitMode::Random { .. } => true,
+            SplitMode::FixedList { shuffled, .. } | SplitMode::WeightedTiers { shuffled, .. } => *shuffled,
+        };
+        random_split && matches!(self.randomness, RandomnessSource::SlotHashes { .. })
+    }
+
+    pub fn random_seed_reveal_deadline(&self) -> u64 {
+        self.create_time.max(self.open_time) + RANDOM_SEED_REVEAL_WINDOW
+    }
+
+    // one claim's entries in claimed_users, claimed_recipients and claimed_amount_records
+    const CLAIM_RECORD_SPACE: usize = 32 + 32 + 8;
+
+    // Account size with room for `claims` claim records. Packets start with none and grow by one
+    // record per claim (record_claim), since RED_PACKET_MAX_CLAIMERS records would not fit in the
+    // 10 KiB a program can allocate when creating an account.
+    pub fn space(claims: u32) -> usize {
+        8 + RedPacket::INIT_SPACE - RED_PACKET_MAX_CLAIMERS as usize * RedPacket::CLAIM_RECORD_SPACE + claims as usize * RedPacket::CLAIM_RECORD_SPACE
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimReceipt {
+    pub red_packet: Pubkey,
+    pub red_packet_create_slot: u64, // a receipt of an earlier red packet at the same address does not count as a claim
+    pub claimer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub claim_time: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PasswordCommit {
+    pub commitment: [u8; 32],
+    pub slot: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RandomCommit {
+    pub slot: u64, // the claim share is drawn from this slot's hash
+}
+
+#[event]
+pub struct RedPacketCreated {
+    pub red_packet: Pubkey,
+    pub creator: Pubkey,
+    pub token_type: AssetKind,
+    pub token_address: Pubkey,
+    pub total_number: u32,
+    pub total_amount: u64,
+    pub create_time: u64,
+    pub open_time: u64,
+    pub expiry: u64,
+}
+
+#[event]
+pub struct RedPacketClaimed {
+    pub red_packet: Pubkey,
+    pub claimer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub claimed_number: u32,
+    pub claimed_amount: u64,
+}
+
+#[event]
+pub struct RedPacketToppedUp {
+    pub red_packet: Pubkey,
+    pub additional_number: u32,
+    pub additional_amount: u64,
+    pub total_number: u32,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct RedPacketWithdrawn {
+    pub red_packet: Pubkey,
+    pub creator: Pubkey,
+    pub refunded_amount: u64,
+}
+
+#[event]
+pub struct RedPacketCancelled {
+    pub red_packet: Pubkey,
+    pub creator: Pubkey,
+    pub refunded_amount: u64,
+}
+
+// emitted when the last claim closes a fully claimed red packet
+#[event]
+pub struct RedPacketClosed {
+    pub red_packet: Pubkey,
+    pub creator: Pubkey,
+}
+
+#[event]
+pub struct RedPacketExpiryUpdated {
+    pub red_packet: Pubkey,
+    pub old_expiry: u64,
+    pub new_expiry: u64,
+}
+
+#[event]
+pub struct RedPacketClaimSignerUpdated {
+    pub red_packet: Pubkey,
+    pub old_signer: Pubkey,
+    pub new_signer: Pubkey,
+    pub claims_paused: bool,
+}
+
+#[event]
+pub struct RedPacketClaimAuthorizationUpdated {
+    pub red_packet: Pubkey,
+    pub old_authorization: ClaimAuthorization,
+    pub new_authorization: ClaimAuthorization,
+}
+
 pub fn initialize_red_packet(
     red_packet: &mut Account<RedPacket>,
     creator: Pubkey,
-    total_number: u8,
+    address: RedPacketAddress,
+    bump: u8,
+    total_number: u32,
     total_amount: u64,
     create_time: u64,
     duration: u64,
-    token_type: u8,
+    open_time: u64,
+    token_type: AssetKind,
     token_address: Pubkey,
-    if_spilt_random: bool,
+    split_mode: SplitMode,
+    use_claim_receipts: bool,
     pubkey_for_claim_signature: Pubkey,
+    claim_authorization: ClaimAuthorization,
+    randomness: RandomnessSource,
     name: String,
     message: String,
 ) {
     red_packet.set_inner(RedPacket {
         creator,
+        address,
+        bump,
         total_number,
         claimed_number: 0,
         total_amount,
         claimed_amount: 0,
         create_time,
+        create_slot: Clock::get().unwrap().slot,
         duration,
+        open_time,
         token_type,
         token_address,
-        if_spilt_random,
+        split_mode,
+        use_claim_receipts,
         claimed_users: vec![],
+        claimed_recipients: vec![],
         claimed_amount_records: vec![],
         pubkey_for_claim_signature,
+        claim_authorization,
+        randomness,
+        random_seed: None,
+        cancelled: false,
+        claims_paused: false,
         name,
         message,
     });
 }
 
-fn calculate_claim_amount(red_packet: &Account<RedPacket>, signer_key: Pubkey) -> u64 {
-    let claim_amount: u64;
+fn created_event(red_packet: &Account<RedPacket>) -> RedPacketCreated {
+    RedPacketCreated {
+        red_packet: red_packet.key(),
+        creator: red_packet.creator,
+        token_type: red_packet.token_type,
+        token_address: red_packet.token_address,
+        total_number: red_packet.total_number,
+        total_amount: red_packet.total_amount,
+        create_time: red_packet.create_time,
+        open_time: red_packet.open_time,
+        expiry: red_packet.create_time + red_packet.duration,
+    }
+}
+
+fn claimed_event(red_packet: &Account<RedPacket>, claimer: Pubkey, recipient: Pubkey, amount: u64) -> RedPacketClaimed {
+    RedPacketClaimed {
+        red_packet: red_packet.key(),
+        claimer,
+        recipient,
+        amount,
+        claimed_number: red_packet.claimed_number,
+        claimed_amount: red_packet.claimed_amount,
+    }
+}
+
+fn withdrawn_event(red_packet: &Account<RedPacket>, refunded_amount: u64) -> RedPacketWithdrawn {
+    RedPacketWithdrawn {
+        red_packet: red_packet.key(),
+        creator: red_packet.creator,
+        refunded_amount,
+    }
+}
+
+fn validate_create_params(total_number: u32, total_amount: u64, create_time: u64, duration: u64, open_time: u64, split_mode: &SplitMode, use_claim_receipts: bool, claim_authorization: &ClaimAuthorization) -> Result<()> {
+    // params check
+    require!(total_number > 0, CustomError::InvalidTotalNumber);
+    // without claim receipts every claim is recorded inside the red packet account
+    require!(use_claim_receipts || total_number <= constants::RED_PACKET_MAX_CLAIMERS as u32, CustomError::InvalidTotalNumber);
+    require!(total_amount > 0 , CustomError::InvalidTotalAmount);
+    split::validate_split_mode(total_number, total_amount, split_mode)?;
+    validate_claim_authorization(claim_authorization)?;
+
+    // time check
+    let _current_time = Clock::get().unwrap().unix_timestamp;
+    require!(_current_time.abs_diff(create_time as i64) < 120, CustomError::InvalidCreateTime);
+    require!(create_time + duration > _current_time as u64, CustomError::InvalidExpiryTime);
+    // open_time is independent of the create_time seed, so a packet can be prepared long before it opens
+    require!(open_time < create_time + duration, CustomError::InvalidOpenTime);
+    Ok(())
+}
+
+fn validate_claim_authorization(claim_authorization: &ClaimAuthorization) -> Result<()> {
+    if let ClaimAuthorization::MultiSignature { signers, threshold } = claim_authorization {
+        require!(*threshold > 0 && *threshold as usize <= signers.len(), CustomError::InvalidClaimSigners);
+        require!(signers.len() <= RED_PACKET_MAX_CLAIM_SIGNERS as usize, CustomError::InvalidClaimSigners);
+        require!(signers.iter().enumerate().all(|(i, signer)| !signers[..i].contains(signer)), CustomError::InvalidClaimSigners);
+    }
+    Ok(())
+}
+
+// Funds a new red packet of either asset kind, returning the token address to record
+fn fund_red_packet<'info>(
+    asset_kind: AssetKind,
+    signer: &Signer<'info>,
+    red_packet: &Account<'info, RedPacket>,
+    token_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    vault: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+    system_program: &Program<'info, System>,
+    total_amount: u64,
+) -> Result<Pubkey> {
+    match asset_kind {
+        AssetKind::Native => {
+            require!(token_mint.is_none() && token_account.is_none() && vault.is_none(), CustomError::InvalidAccountForNativeToken);
+            fund_with_native_token(signer, red_packet, system_program, total_amount)?;
+            Ok(Pubkey::default())
+        }
+        AssetKind::SplToken => {
+            let (Some(token_mint), Some(token_account), Some(vault), Some(token_program)) = (token_mint, token_account, vault, token_program) else {
+                return err!(CustomError::InvalidInitialParamsForTokenAccount);
+            };
+            fund_with_spl_token(signer, token_account, vault, token_mint, token_program, total_amount)?;
+            Ok(token_mint.key())
+        }
+    }
+}
+
+fn fund_with_spl_token<'info>(
+    signer: &Signer<'info>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    total_amount: u64,
+) -> Result<()> {
+    // check if the creator has enough tokens
+    require!(token_account.amount >= total_amount, CustomError::InsufficientTokenBalance);
+
+    transfer::transfer_tokens(
+        token_account,
+        vault,
+        &total_amount,
+        token_mint,
+        &signer.to_account_info(),
+        token_program,
+        &[]
+    )
+}
+
+// Pays the rent of `number` more claim records up front, so the account can grow in record_claim
+// without charging the claimers. The creator gets it back when the red packet is closed.
+fn fund_claim_records<'info>(
+    signer: &Signer<'info>,
+    red_packet: &Account<'info, RedPacket>,
+    system_program: &Program<'info, System>,
+    number: u32,
+) -> Result<()> {
+    if red_packet.use_claim_receipts {
+        return Ok(());
+    }
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(RedPacket::space(number)) - rent.minimum_balance(RedPacket::space(0));
+    fund_with_native_token(signer, red_packet, system_program, lamports)
+}
+
+fn fund_with_native_token<'info>(
+    signer: &Signer<'info>,
+    red_packet: &Account<'info, RedPacket>,
+    system_program: &Program<'info, System>,
+    total_amount: u64,
+) -> Result<()> {
+    require!(signer.lamports() >= total_amount, CustomError::InsufficientTokenBalance);
+    // Transfer tokens from initializer to PDA account (red packet account)
+    let ix = anchor_lang::solana_program::system_instruction::transfer(
+        &signer.key(),
+        &red_packet.key(),
+        total_amount
+    );
+    anchor_lang::solana_program::program::invoke(
+        &ix,
+        &[
+            signer.to_account_info(),
+            red_packet.to_account_info(),
+            system_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
+// Checks and authorizes a claim, returning the claim amount and the current time
+fn prepare_claim(
+    red_packet: &mut Account<RedPacket>,
+    proof: &ClaimProof,
+    claim_receipt: Option<&ClaimReceipt>,
+    password_commit: Option<&PasswordCommit>,
+    random_commit: Option<&RandomCommit>,
+    instruction_sysvar: &AccountInfo,
+    slot_hashes: &AccountInfo,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+) -> Result<(u64, u64)> {
+    require!(!red_packet.cancelled, CustomError::RedPacketCancelled);
+    require!(!red_packet.claims_paused, CustomError::ClaimsPaused);
+    let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+    let expiry = red_packet.create_time + red_packet.duration;
+    require!(current_time < expiry, CustomError::RedPacketExpired);
+    require!(current_time >= red_packet.open_time, CustomError::RedPacketNotOpen);
+    require!(red_packet.claimed_number < red_packet.total_number, CustomError::RedPacketAllClaimed);
+    check_not_claimed(red_packet, claim_receipt, claimer)?;
+
+    // verify signature or allowlist proof
+    let fixed_amount = authorize_claim(red_packet, proof, instruction_sysvar, password_commit, claimer, recipient)?;
+    let committed_slot_hash = if red_packet.needs_random_commit() { Some(committed_slot_hash(random_commit, slot_hashes)?) } else { None };
+    let claim_amount = match fixed_amount {
+        Some(amount) => {
+            require!(split::accepts_fixed_amounts(&red_packet.split_mode), CustomError::InvalidSplitConfig);
+            amount
+        }
+        None => calculate_claim_amount(red_packet, *claimer, committed_slot_hash)?,
+    };
+
+    // check if the claim amount is valid
+    require!(red_packet.claimed_amount + claim_amount <= red_packet.total_amount, CustomError::InvalidClaimAmount);
+    // a versioned claim signature can cap what its claimer receives
+    if let ClaimProof::SignatureV1 { max_amount, .. } = proof {
+        require!(*max_amount == 0 || claim_amount <= *max_amount, CustomError::ClaimAmountAboveSignedMax);
+    }
+    Ok((claim_amount, current_time))
+}
+
+fn pay_out_native_token(red_packet: &AccountInfo, recipient: &AccountInfo, claim_amount: u64) -> Result<()> {
+    // Transfer SOL using native transfer
+    **red_packet.try_borrow_mut_lamports()? -= claim_amount;
+    **recipient.try_borrow_mut_lamports()? += claim_amount;
+    Ok(())
+}
+
+fn pay_out_spl_token<'info>(
+    red_packet: &Account<'info, RedPacket>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    recipient_token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    claim_amount: u64,
+) -> Result<()> {
+    // Transfer SPL tokens from vault to recipient's token account
+    // Signer seeds for PDA authority
+    let binding = red_packet.creator.key();
+    let address_seed = red_packet.address_seed();
+    let seeds = &[binding.as_ref(), address_seed.as_ref(), &[red_packet.bump]];
+    let signer_seeds = &[&seeds[..]];
+    transfer::transfer_tokens(
+        vault,
+        recipient_token_account,
+        &claim_amount,
+        token_mint,
+        &red_packet.to_account_info(),
+        token_program,
+        signer_seeds
+    )
+}
+
+// Sends the unclaimed remainder back to the creator, closing the vault for SPL red packets, and
+// returns the amount sent. Native lamports are moved out of the packet account, leaving its rent in place.
+fn refund_remainder<'info>(
+    red_packet: &Account<'info, RedPacket>,
+    signer: &Signer<'info>,
+    token_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    vault: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+) -> Result<u64> {
+    match red_packet.token_type {
+        AssetKind::Native => {
+            require!(token_mint.is_none() && token_account.is_none() && vault.is_none(), CustomError::InvalidAccountForNativeToken);
+            let remainder = unclaimed_amount(red_packet);
+            pay_out_native_token(&red_packet.to_account_info(), signer, remainder)?;
+            Ok(remainder)
+        }
+        AssetKind::SplToken => {
+            let (Some(token_mint), Some(token_account), Some(vault), Some(token_program)) = (token_mint, token_account, vault, token_program) else {
+                return err!(CustomError::InvalidInitialParamsForTokenAccount);
+            };
+            require_keys_eq!(token_mint.key(), red_packet.token_address, CustomError::InvalidTokenType);
+            empty_and_close_vault(red_packet, vault, token_account, token_mint, token_program, signer)
+        }
+    }
+}
+
+// What a withdraw refunds: nothing once cancel_red_packet has refunded the remainder
+fn unclaimed_amount(red_packet: &RedPacket) -> u64 {
+    if red_packet.cancelled { 0 } else { red_packet.total_amount - red_packet.claimed_amount }
+}
+
+fn check_withdrawable(red_packet: &RedPacket, signer: &Pubkey) -> Result<()> {
+    let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+    let expiry = red_packet.create_time + red_packet.duration;
+    // a cancelled or fully claimed packet has nothing left to claim, so its rent can be reclaimed right away
+    let fully_claimed = red_packet.claimed_number == red_packet.total_number;
+    require!(red_packet.cancelled || fully_claimed || current_time >= expiry, CustomError::RedPacketNotExpired);
+    require!(red_packet.creator == *signer, CustomError::Unauthorized);
+    Ok(())
+}
+
+// Sends the unclaimed tokens to the creator and closes the vault, returning its rent, and returns
+// the amount sent. The whole vault balance goes, since tokens sent to the vault by others would
+// make close_account fail.
+fn empty_and_close_vault<'info>(
+    red_packet: &Account<'info, RedPacket>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    signer: &Signer<'info>,
+) -> Result<u64> {
+    // Signer seeds for PDA authority
+    let binding = red_packet.creator.key();
+    let address_seed = red_packet.address_seed();
+    let seeds = &[binding.as_ref(), address_seed.as_ref(), &[red_packet.bump]];
+    drain_and_close_vault(&red_packet.to_account_info(), &[&seeds[..]], vault, token_account, token_mint, token_program, signer)
+}
+
+// empty_and_close_vault for any vault authority, also used for legacy::LegacyRedPacket vaults
+fn drain_and_close_vault<'info>(
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    destination: &AccountInfo<'info>,
+) -> Result<u64> {
+    // Transfer SPL tokens from vault to creator's token account
+    let amount = vault.amount;
+    transfer::transfer_tokens(
+        vault,
+        token_account,
+        &amount,
+        token_mint,
+        authority,
+        token_program,
+        signer_seeds
+    )?;
+
+    let accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: destination.to_account_info(),
+        authority: authority.clone(),
+    };
+    close_account(CpiContext::new_with_signer(token_program.to_account_info(), accounts, signer_seeds))?;
+    Ok(amount)
+}
+
+// Closes the emptied vault, returning its rent to the destination
+fn close_vault<'info>(
+    red_packet: &Account<'info, RedPacket>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let binding = red_packet.creator.key();
+    let address_seed = red_packet.address_seed();
+    let seeds = &[binding.as_ref(), address_seed.as_ref(), &[red_packet.bump]];
+    let signer_seeds = &[&seeds[..]];
+    let accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: destination.to_account_info(),
+        authority: red_packet.to_account_info(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        accounts,
+        signer_seeds,
+    );
+    close_account(cpi_context)
+}
+
+// Transfer all redpacket lamports (remaining balance + rent) to the destination
+fn close_red_packet(red_packet: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    let dest_starting_lamports = destination.lamports();
+    let red_packet_lamports = red_packet.lamports();
+    **red_packet.try_borrow_mut_lamports()? = 0;
+    **destination.try_borrow_mut_lamports()? = dest_starting_lamports
+        .checked_add(red_packet_lamports)
+        .unwrap();
+    Ok(())
+}
+
+fn check_not_claimed(red_packet: &RedPacket, claim_receipt: Option<&ClaimReceipt>, claimer: &Pubkey) -> Result<()> {
+    match claim_receipt {
+        Some(receipt) => {
+            require!(red_packet.use_claim_receipts, CustomError::InvalidClaimReceipt);
+            // a receipt created by this instruction is still zeroed, one left by an earlier red packet at
+            // this address is overwritten
+            require!(receipt.claimer == Pubkey::default() || receipt.red_packet_create_slot != red_packet.create_slot, CustomError::RedPacketClaimed);
+        }
+        None => {
+            require!(!red_packet.use_claim_receipts, CustomError::InvalidClaimReceipt);
+            require!(!red_packet.claimed_users.contains(claimer), CustomError::RedPacketClaimed);
+        }
+    }
+    Ok(())
+}
+
+fn record_claim(
+    red_packet: &mut Account<RedPacket>,
+    claim_receipt: Option<&mut Account<ClaimReceipt>>,
+    claimer: Pubkey,
+    recipient: Pubkey,
+    claim_amount: u64,
+    claim_time: u64,
+) -> Result<()> {
+    match claim_receipt {
+        Some(receipt) => receipt.set_inner(ClaimReceipt {
+            red_packet: red_packet.key(),
+            red_packet_create_slot: red_packet.create_slot,
+            claimer,
+            recipient,
+            amount: claim_amount,
+            claim_time,
+        }),
+        None => {
+            red_packet.claimed_users.push(claimer);
+            red_packet.claimed_recipients.push(recipient);
+            red_packet.claimed_amount_records.push(claim_amount);
+            // room for the new record, its rent was paid by fund_claim_records
+            let space = RedPacket::space(red_packet.claimed_users.len() as u32);
+            let account = red_packet.to_account_info();
+            if account.data_len() < space {
+                account.realloc(space, false)?;
+            }
+        }
+    }
+    red_packet.claimed_number += 1;
+    red_packet.claimed_amount += claim_amount;
+    Ok(())
+}
 
+// Returns the claim amount pinned by the authorization, if any
+fn authorize_claim(
+    red_packet: &Account<RedPacket>,
+    proof: &ClaimProof,
+    instruction_sysvar: &AccountInfo,
+    password_commit: Option<&PasswordCommit>,
+    claimer: &Pubkey,
+    recipient: &Pubkey,
+) -> Result<Option<u64>> {
+    match (&red_packet.claim_authorization, proof) {
+        (ClaimAuthorization::Signature, ClaimProof::None) => {
+            let message = claim_message::legacy_claim_message(&red_packet.key(), claimer, recipient);
+            require!(verify_claim_signature(instruction_sysvar, &message, red_packet.pubkey_for_claim_signature.as_ref()).is_ok(), CustomError::InvalidSignature);
+            Ok(None)
+        }
+        (ClaimAuthorization::SignatureV1, ClaimProof::SignatureV1 { deadline, max_amount }) => {
+            let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+            require!(current_time <= *deadline, CustomError::ClaimSignatureExpired);
+            // the program id in the message keeps signatures from being replayed against another deployment
+            let message = claim_message::claim_message_v1(&crate::ID, &red_packet.key(), claimer, recipient, *deadline, *max_amount);
+            require!(verify_claim_signature(instruction_sysvar, &message, red_packet.pubkey_for_claim_signature.as_ref()).is_ok(), CustomError::InvalidSignature);
+            Ok(None)
+        }
+        (ClaimAuthorization::MerkleAllowlist { root }, ClaimProof::Merkle { proof, amount }) => {
+            require!(merkle::verify_proof(proof, root, merkle::allowlist_leaf(claimer, *amount)), CustomError::InvalidMerkleProof);
+            Ok(if *amount > 0 { Some(*amount) } else { None })
+        }
+        (ClaimAuthorization::Password { hash }, ClaimProof::Password { phrase }) => {
+            let commit = password_commit.ok_or(error!(CustomError::PasswordNotCommitted))?;
+            // the commitment must have landed before the phrase became visible in this transaction
+            require!(commit.slot < Clock::get()?.slot, CustomError::PasswordNotCommitted);
+            require!(commit.commitment == password::password_commitment(&red_packet.key(), claimer, phrase), CustomError::InvalidPassword);
+            require!(*hash == password::password_hash(&red_packet.key(), phrase), CustomError::InvalidPassword);
+            Ok(None)
+        }
+        (ClaimAuthorization::MultiSignature { signers, threshold }, ClaimProof::None) => {
+            let message = claim_message::legacy_claim_message(&red_packet.key(), claimer, recipient);
+            verify_claim_signatures(instruction_sysvar, &message, signers, *threshold)?;
+            Ok(None)
+        }
+        (ClaimAuthorization::MultiSignature { signers, threshold }, ClaimProof::SignatureV1 { deadline, max_amount }) => {
+            let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+            require!(current_time <= *deadline, CustomError::ClaimSignatureExpired);
+            let message = claim_message::claim_message_v1(&crate::ID, &red_packet.key(), claimer, recipient, *deadline, *max_amount);
+            verify_claim_signatures(instruction_sysvar, &message, signers, *threshold)?;
+            Ok(None)
+        }
+        (ClaimAuthorization::Secp256k1 { eth_address }, ClaimProof::None) => {
+            let message = claim_message::legacy_claim_message(&red_packet.key(), claimer, recipient);
+            verify_claim_secp256k1_signature(instruction_sysvar, &message, eth_address)?;
+            Ok(None)
+        }
+        (ClaimAuthorization::Secp256k1 { eth_address }, ClaimProof::SignatureV1 { deadline, max_amount }) => {
+            let current_time: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+            require!(current_time <= *deadline, CustomError::ClaimSignatureExpired);
+            let message = claim_message::claim_message_v1(&crate::ID, &red_packet.key(), claimer, recipient, *deadline, *max_amount);
+            verify_claim_secp256k1_signature(instruction_sysvar, &message, eth_address)?;
+            Ok(None)
+        }
+        _ => err!(CustomError::InvalidClaimProof),
+    }
+}
+
+// The hash of the slot the claimer's commit_random_claim landed in
+fn committed_slot_hash(random_commit: Option<&RandomCommit>, slot_hashes: &AccountInfo) -> Result<[u8; 32]> {
+    let commit = random_commit.ok_or(error!(CustomError::RandomClaimNotCommitted))?;
+    let current_slot = Clock::get()?.slot;
+    // the hash only exists once the commit slot is over
+    require!(commit.slot < current_slot, CustomError::RandomClaimNotCommitted);
+    // The draw also depends on the amount and shares left, which other claims change. Once the
+    // hash is known, a claimer free to pick the moment could wait for a large share or top tier.
+    require!(current_slot <= commit.slot + RANDOM_CLAIM_WINDOW_SLOTS, CustomError::RandomCommitExpired);
+    randomness::slot_hash(slot_hashes, commit.slot)?.ok_or(error!(CustomError::RandomCommitExpired))
+}
+
+fn calculate_claim_amount(red_packet: &mut Account<RedPacket>, signer_key: Pubkey, committed_slot_hash: Option<[u8; 32]>) -> Result<u64> {
     let remaining_amount = red_packet.total_amount - red_packet.claimed_amount;
     if red_packet.total_number - red_packet.claimed_number == 1 {
-        return remaining_amount;
+        return Ok(remaining_amount);
     }
 
-    if red_packet.if_spilt_random == constants::RED_PACKET_SPILT_EQUAL {
-        claim_amount = red_packet.total_amount / red_packet.total_number as u64;   
-    } else {
-        let random_value = generate_random_number(red_packet.key(), signer_key);
-        let claim_value = random_value % ((remaining_amount * 2) / (red_packet.total_number - red_packet.claimed_number) as u64);
-        claim_amount = if claim_value == 0 { 1 } else { claim_value };
-    } 
+    let remaining_number = (red_packet.total_number - red_packet.claimed_number) as u64;
+    let claim_amount = match &red_packet.split_mode {
+        SplitMode::Equal => remaining_amount / remaining_number,
+        SplitMode::Random { split } => {
+            let split = split.clone();
+            let random_value = generate_random_number(red_packet, signer_key, committed_slot_hash)?;
+            match split {
+                RandomSplit::Legacy => {
+                    let claim_value = random_value % ((remaining_amount * 2) / remaining_number);
+                    if claim_value == 0 { 1 } else { claim_value }
+                }
+                RandomSplit::DoubleAverage { min_share, max_share } => {
+                    split::double_average_share(remaining_amount, remaining_number, min_share, max_share, random_value)
+                }
+            }
+        }
+        SplitMode::FixedList { shuffled, .. } | SplitMode::WeightedTiers { shuffled, .. } => {
+            let pick = if *shuffled { generate_random_number(red_packet, signer_key, committed_slot_hash)? % remaining_number } else { 0 };
+            let total_amount = red_packet.total_amount;
+            split::take_tier_share(&mut red_packet.split_mode, total_amount, pick)
+        }
+    };
     msg!("claim_amount: {}", claim_amount);
-    return claim_amount;
+    Ok(claim_amount)
 }
 
-fn generate_random_number(redpacket_key: Pubkey, signer_key: Pubkey) -> u64 {
-    let clock = Clock::get().unwrap();
-    let current_timestamp = clock.unix_timestamp;
+fn generate_random_number(red_packet: &Account<RedPacket>, signer_key: Pubkey, committed_slot_hash: Option<[u8; 32]>) -> Result<u64> {
+    match red_packet.randomness {
+        RandomnessSource::Timestamp => {
+            let clock = Clock::get().unwrap();
+            let current_timestamp = clock.unix_timestamp;
 
-    let seed = format!("{}{}{}", redpacket_key, signer_key, current_timestamp);
-    let hash_value = hash(seed.as_bytes()); 
-    u64::from_le_bytes(hash_value.to_bytes()[0..8].try_into().unwrap())
+            let seed = format!("{}{}{}", red_packet.key(), signer_key, current_timestamp);
+            let hash_value = hash(seed.as_bytes()); 
+            Ok(u64::from_le_bytes(hash_value.to_bytes()[0..8].try_into().unwrap()))
+        }
+        RandomnessSource::SlotHashes { .. } => {
+            let slot_hash = committed_slot_hash.ok_or(error!(CustomError::RandomClaimNotCommitted))?;
+            // commit_random_claim only accepts commits once the seed is final
+            let seed = red_packet.random_seed.unwrap_or_default();
+            Ok(randomness::slot_hashes_random_number(&seed, &slot_hash, &red_packet.key(), &signer_key))
+        }
+    }
 }
 
-pub fn verify_claim_signature(
-    instruction_sysvar: &AccountInfo,
-    red_packet_key: &[u8],
-    claimer_key: &[u8],
-    expected_public_key_arr: &[u8]
-) -> Result<()> {
+// The signature verify instruction right before the current one, with its index in the transaction
+fn load_preceding_instruction(instruction_sysvar: &AccountInfo) -> Result<(u16, Instruction)> {
     let current_index = load_current_index_checked(instruction_sysvar)?;
     if current_index == 0 {
         msg!("fail to get instruction from current_index: {}", current_index);
         return Err(error!(CustomError::InvalidSignature));
     }
+    let instruction = load_instruction_at_checked((current_index - 1) as usize, instruction_sysvar)?;
+    Ok((current_index - 1, instruction))
+}
 
-    let ed25519_instruction = load_instruction_at_checked((current_index - 1) as usize, instruction_sysvar)?;
-    
-    // Verify the content of the Ed25519 instruction
-    let instruction_data = ed25519_instruction.data;
-    if instruction_data.len() < 2 {
-        msg!("fail to get instruction_data from instruction: {}", instruction_data.len());
+fn parse_ed25519_instruction(ed25519_instruction: &Instruction) -> Result<Vec<ed25519::Ed25519Signature<'_>>> {
+    ed25519::parse_instruction(ed25519_instruction).map_err(|err| {
+        msg!("invalid ed25519 instruction: {:?}", err);
+        error!(CustomError::InvalidSignature)
+    })
+}
+
+pub fn verify_claim_signature(
+    instruction_sysvar: &AccountInfo,
+    expected_message: &[u8],
+    expected_public_key_arr: &[u8]
+) -> Result<()> {
+    let (_, ed25519_instruction) = load_preceding_instruction(instruction_sysvar)?;
+    let signatures = parse_ed25519_instruction(&ed25519_instruction)?;
+    let [signature] = signatures.as_slice() else {
+        msg!("fail to get num_signatures from instruction: {}", signatures.len());
+        return Err(error!(CustomError::InvalidSignature));
+    };
+
+    // Verify public key
+    if signature.public_key != expected_public_key_arr {
+        msg!("fail to verify expected_public_key: {:?} ", expected_public_key_arr);
         return Err(error!(CustomError::InvalidSignature));
     }
 
-    let num_signatures = instruction_data[0];
-    if num_signatures != 1 {
-        msg!("fail to get num_signatures from instruction: {}", num_signatures);
+    // Verify message
+    if signature.message != expected_message {
         return Err(error!(CustomError::InvalidSignature));
     }
 
-    // Parse Ed25519SignatureOffsets
-    let offsets: Ed25519SignatureOffsets = Ed25519SignatureOffsets::try_from_slice(&instruction_data[2..16])?;
+    Ok(())
+}
 
-    // Verify public key
-    let pubkey_start = offsets.public_key_offset as usize;
-    let pubkey_end = pubkey_start + 32;
-    if &instruction_data[pubkey_start..pubkey_end] != expected_public_key_arr {
-        msg!("fail to verify pubkey: {} ", pubkey_start);
-        msg!("fail to verify expected_public_key: {:?} ", expected_public_key_arr);
+// Counts the authorized signers with a signature over expected_message in the preceding Ed25519
+// instruction. Signatures from other keys or over other messages are ignored, and a signer listed
+// twice counts once.
+pub fn verify_claim_signatures(
+    instruction_sysvar: &AccountInfo,
+    expected_message: &[u8],
+    signers: &[Pubkey],
+    threshold: u8,
+) -> Result<()> {
+    let (_, ed25519_instruction) = load_preceding_instruction(instruction_sysvar)?;
+    let signatures = parse_ed25519_instruction(&ed25519_instruction)?;
+
+    let mut approved = vec![false; signers.len()];
+    for signature in signatures.iter().filter(|signature| signature.message == expected_message) {
+        if let Some(index) = signers.iter().position(|signer| signer.as_ref() == signature.public_key) {
+            approved[index] = true;
+        }
+    }
+    let approvals = approved.iter().filter(|approved| **approved).count();
+    if approvals < threshold as usize {
+        msg!("claim approved by {} of {} required signers", approvals, threshold);
         return Err(error!(CustomError::InvalidSignature));
     }
 
-    // Verify message
-    let expected_message = [red_packet_key, claimer_key].concat();
-    let msg_start = offsets.message_data_offset as usize;
-    let msg_end = msg_start + offsets.message_data_size as usize;
-    if &instruction_data[msg_start..msg_end] != expected_message {
+    Ok(())
+}
+
+// Same checks as verify_claim_signature for a Secp256k1 program instruction, whose signature the
+// precompile has already recovered to eth_address
+pub fn verify_claim_secp256k1_signature(
+    instruction_sysvar: &AccountInfo,
+    expected_message: &[u8],
+    expected_eth_address: &[u8; 20],
+) -> Result<()> {
+    let (index, secp256k1_instruction) = load_preceding_instruction(instruction_sysvar)?;
+    // instruction indexes in Secp256k1 offsets are a u8
+    let index = u8::try_from(index).map_err(|_| error!(CustomError::InvalidSignature))?;
+    let signatures = secp256k1::parse_instruction(&secp256k1_instruction, index).map_err(|err| {
+        msg!("invalid secp256k1 instruction: {:?}", err);
+        error!(CustomError::InvalidSignature)
+    })?;
+    let [signature] = signatures.as_slice() else {
+        msg!("fail to get num_signatures from instruction: {}", signatures.len());
+        return Err(error!(CustomError::InvalidSignature));
+    };
+
+    if signature.eth_address != expected_eth_address {
+        msg!("fail to verify expected_eth_address: {:?} ", expected_eth_address);
+        return Err(error!(CustomError::InvalidSignature));
+    }
+    // keccak256(message) signed by a backend key, or the EIP-191 digest personal_sign produces
+    if signature.message != expected_message && signature.message != secp256k1::personal_sign_message(expected_message) {
         return Err(error!(CustomError::InvalidSignature));
     }
 
@@ -510,5 +2056,53 @@ pub enum CustomError {
     #[msg("All the red packet has been claimed.")]
     RedPacketAllClaimed,
     #[msg("You are not authorized to perform this action.")]
-    Unauthorized
+    Unauthorized,
+    #[msg("Claim receipt account is missing or not expected for this red packet.")]
+    InvalidClaimReceipt,
+    #[msg("The claim proof does not match the red packet's claim authorization.")]
+    InvalidClaimProof,
+    #[msg("Invalid merkle proof.")]
+    InvalidMerkleProof,
+    #[msg("No password commitment from an earlier slot was found.")]
+    PasswordNotCommitted,
+    #[msg("Invalid password.")]
+    InvalidPassword,
+    #[msg("Invalid random seed.")]
+    InvalidRandomSeed,
+    #[msg("The creator has not revealed the random seed yet.")]
+    RandomSeedNotRevealed,
+    #[msg("Invalid split config.")]
+    InvalidSplitConfig,
+    #[msg("The share tiers do not add up to the total number and amount.")]
+    InvalidShareTiers,
+    #[msg("The red packet has been cancelled.")]
+    RedPacketCancelled,
+    #[msg("Invalid open time.")]
+    InvalidOpenTime,
+    #[msg("The red packet is not open yet.")]
+    RedPacketNotOpen,
+    #[msg("The claim signature has expired.")]
+    ClaimSignatureExpired,
+    #[msg("The claim amount is above the maximum covered by the claim signature.")]
+    ClaimAmountAboveSignedMax,
+    #[msg("The creator has paused claims.")]
+    ClaimsPaused,
+    #[msg("Invalid claim signers or threshold.")]
+    InvalidClaimSigners,
+    #[msg("The red packet has not been closed yet.")]
+    RedPacketNotClosed,
+    #[msg("No random claim commitment from an earlier slot was found.")]
+    RandomClaimNotCommitted,
+    #[msg("The claim did not follow the random claim commitment within its window.")]
+    RandomCommitExpired,
+    #[msg("The random seed can no longer be revealed.")]
+    RandomSeedRevealExpired,
+    #[msg("The account is not a red packet created by the first program version.")]
+    NotLegacyRedPacket,
+    #[msg("The claim authorization does not use the claim signer key.")]
+    ClaimSignerNotUsed,
+    #[msg("The claim authorization can only be replaced by one of the same kind.")]
+    InvalidClaimAuthorization,
+    #[msg("The random claim commitment can only be closed once its slot hash has expired.")]
+    RandomCommitNotExpired
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hashv;
+
+// Leaves and inner nodes are hashed with different prefixes so a proof can never
+// pass off an inner node as a leaf.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+// amount == 0 means the claimer is allowed but gets the packet's regular split
+pub fn allowlist_leaf(claimer: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, claimer.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+// Sibling pairs are sorted before hashing, so proofs carry no left/right flags.
+pub fn verify_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[NODE_PREFIX, &node, sibling]).to_bytes()
+        } else {
+            hashv(&[NODE_PREFIX, sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
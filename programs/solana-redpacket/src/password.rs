@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hashv;
+
+// Stored in ClaimAuthorization::Password at creation
+pub fn password_hash(red_packet: &Pubkey, phrase: &str) -> [u8; 32] {
+    hashv(&[red_packet.as_ref(), phrase.as_bytes()]).to_bytes()
+}
+
+// Committed by the claimer before revealing the phrase. Binding the claimer key means a
+// commitment copied from the mempool is useless to anyone else.
+pub fn password_commitment(red_packet: &Pubkey, claimer: &Pubkey, phrase: &str) -> [u8; 32] {
+    hashv(&[red_packet.as_ref(), claimer.as_ref(), phrase.as_bytes()]).to_bytes()
+}
@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hashv;
+use std::cmp::Ordering;
+
+// SlotHashes is too large to deserialize on-chain, so it is read in place:
+// u64 entry count, then (slot: u64, hash: [u8; 32]) entries, most recent first.
+const SLOT_HASHES_HEADER: usize = 8;
+const SLOT_HASH_ENTRY: usize = 8 + 32;
+
+// The hash of `slot`, or None once it has dropped out of the sysvar (after about 512 slots)
+pub fn slot_hash(slot_hashes: &AccountInfo, slot: u64) -> Result<Option<[u8; 32]>> {
+    let data = slot_hashes.try_borrow_data()?;
+    Ok(find_slot_hash(&data, slot))
+}
+
+// Binary search over the entries, which are sorted by descending slot
+fn find_slot_hash(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let count = u64::from_le_bytes(data.get(..SLOT_HASHES_HEADER)?.try_into().unwrap()) as usize;
+    let entries = data.get(SLOT_HASHES_HEADER..SLOT_HASHES_HEADER.checked_add(count.checked_mul(SLOT_HASH_ENTRY)?)?)?;
+    let entry_slot = |index: usize| u64::from_le_bytes(entries[index * SLOT_HASH_ENTRY..index * SLOT_HASH_ENTRY + 8].try_into().unwrap());
+
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        match entry_slot(middle).cmp(&slot) {
+            Ordering::Equal => {
+                let start = middle * SLOT_HASH_ENTRY + 8;
+                return Some(entries[start..start + 32].try_into().unwrap());
+            }
+            Ordering::Greater => low = middle + 1,
+            Ordering::Less => high = middle,
+        }
+    }
+    None
+}
+
+pub fn seed_commitment(seed: &[u8; 32]) -> [u8; 32] {
+    hashv(&[seed]).to_bytes()
+}
+
+// slot_hash is the hash of the slot the claimer's commit_random_claim landed in, which did not
+// exist yet when the commit was sent. A commit can only be made again once that hash has left
+// the SlotHashes sysvar (close_random_commit), so retrying until the draw suits the claimer is slow.
+pub fn slot_hashes_random_number(seed: &[u8; 32], slot_hash: &[u8; 32], red_packet: &Pubkey, claimer: &Pubkey) -> u64 {
+    let hash_value = hashv(&[seed, slot_hash, red_packet.as_ref(), claimer.as_ref()]);
+    u64::from_le_bytes(hash_value.to_bytes()[0..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_hashes(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn finds_every_entry() {
+        // newest first, with skipped slots
+        let slots = [120, 119, 117, 110, 109, 100];
+        let data = slot_hashes(&slots);
+        for slot in slots {
+            assert_eq!(find_slot_hash(&data, slot), Some([slot as u8; 32]));
+        }
+    }
+
+    #[test]
+    fn misses_skipped_future_and_evicted_slots() {
+        let data = slot_hashes(&[120, 119, 117, 110]);
+        for slot in [118, 121, 109, 0, u64::MAX] {
+            assert_eq!(find_slot_hash(&data, slot), None);
+        }
+        assert_eq!(find_slot_hash(&slot_hashes(&[]), 5), None);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = slot_hashes(&[120, 119]);
+        assert_eq!(find_slot_hash(&data[..data.len() - 1], 119), None);
+        assert_eq!(find_slot_hash(&data[..4], 120), None);
+        // an entry count that overflows the entry size
+        assert_eq!(find_slot_hash(&u64::MAX.to_le_bytes(), 1), None);
+    }
+}
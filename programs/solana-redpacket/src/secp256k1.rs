@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use solana_program::{instruction::Instruction, secp256k1_program};
+
+// Secp256k1 program instruction data: num_signatures: u8, then one Secp256k1SignatureOffsets per
+// signature, then the Ethereum addresses, signatures (with a trailing recovery id) and messages the
+// offsets point at. The precompile recovers the signer from keccak256(message).
+pub const SIGNATURE_OFFSETS_START: usize = 1;
+pub const SIGNATURE_OFFSETS_SIZE: usize = 11;
+pub const ETH_ADDRESS_SIZE: usize = 20;
+pub const SIGNATURE_SIZE: usize = 64;
+pub const RECOVERY_ID_SIZE: usize = 1;
+
+// EIP-191 (version 0x45) prefix that wallets' personal_sign puts before the message it signs
+pub const PERSONAL_SIGN_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+
+// The message as personal_sign hashes it: the prefix, the message length in decimal, then the message
+pub fn personal_sign_message(message: &[u8]) -> Vec<u8> {
+    let length = message.len().to_string();
+    let mut prefixed = Vec::with_capacity(PERSONAL_SIGN_PREFIX.len() + length.len() + message.len());
+    prefixed.extend_from_slice(PERSONAL_SIGN_PREFIX);
+    prefixed.extend_from_slice(length.as_bytes());
+    prefixed.extend_from_slice(message);
+    prefixed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Secp256k1Error {
+    NotSecp256k1Program,
+    NoSignatures,
+    Truncated,
+    // The precompile would verify data from another instruction than the one parsed here
+    ForeignInstructionIndex,
+    OffsetOutOfBounds,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Secp256k1SignatureOffsets {
+    pub signature_offset: u16,            // offset to secp256k1 signature of 64 bytes followed by the recovery id
+    pub signature_instruction_index: u8,  // instruction index to find signature
+    pub eth_address_offset: u16,          // offset to ethereum address of 20 bytes
+    pub eth_address_instruction_index: u8, // instruction index to find ethereum address
+    pub message_data_offset: u16,         // offset to start of message data
+    pub message_data_size: u16,           // size of message data
+    pub message_instruction_index: u8,    // index of instruction data to get message data
+}
+
+// One signature checked by the Secp256k1 program, borrowed from its instruction data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1Signature<'a> {
+    pub eth_address: &'a [u8],
+    pub signature: &'a [u8],
+    pub recovery_id: u8,
+    pub message: &'a [u8],
+}
+
+// Parses a Secp256k1 program instruction without panicking on any input. Unlike the Ed25519
+// program, instruction indexes are absolute positions in the transaction, so every one of them
+// must equal instruction_index, the position of the parsed instruction.
+pub fn parse_instruction(
+    instruction: &Instruction,
+    instruction_index: u8,
+) -> std::result::Result<Vec<Secp256k1Signature<'_>>, Secp256k1Error> {
+    if instruction.program_id != secp256k1_program::ID {
+        return Err(Secp256k1Error::NotSecp256k1Program);
+    }
+    let data = instruction.data.as_slice();
+    let num_signatures = *data.first().ok_or(Secp256k1Error::Truncated)? as usize;
+    if num_signatures == 0 {
+        return Err(Secp256k1Error::NoSignatures);
+    }
+    let offsets_end = SIGNATURE_OFFSETS_START + num_signatures * SIGNATURE_OFFSETS_SIZE;
+    let offsets_data = data.get(SIGNATURE_OFFSETS_START..offsets_end).ok_or(Secp256k1Error::Truncated)?;
+
+    offsets_data
+        .chunks_exact(SIGNATURE_OFFSETS_SIZE)
+        .map(|chunk| {
+            let offsets = Secp256k1SignatureOffsets::try_from_slice(chunk).map_err(|_| Secp256k1Error::Truncated)?;
+            if offsets.signature_instruction_index != instruction_index
+                || offsets.eth_address_instruction_index != instruction_index
+                || offsets.message_instruction_index != instruction_index
+            {
+                return Err(Secp256k1Error::ForeignInstructionIndex);
+            }
+            let signature = slice(data, offsets.signature_offset, SIGNATURE_SIZE + RECOVERY_ID_SIZE)?;
+            Ok(Secp256k1Signature {
+                eth_address: slice(data, offsets.eth_address_offset, ETH_ADDRESS_SIZE)?,
+                signature: &signature[..SIGNATURE_SIZE],
+                recovery_id: signature[SIGNATURE_SIZE],
+                message: slice(data, offsets.message_data_offset, offsets.message_data_size as usize)?,
+            })
+        })
+        .collect()
+}
+
+fn slice(data: &[u8], offset: u16, size: usize) -> std::result::Result<&[u8], Secp256k1Error> {
+    let start = offset as usize;
+    data.get(start..start + size).ok_or(Secp256k1Error::OffsetOutOfBounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETH_ADDRESS: [u8; 20] = [1; 20];
+    const SIGNATURE: [u8; 65] = {
+        let mut signature = [2; 65];
+        signature[64] = 1;
+        signature
+    };
+    const MESSAGE: &[u8] = b"red packet || claimer || recipient";
+    const INDEX: u8 = 3;
+
+    // Same layout as the client's secp256k1_verify_instruction
+    fn valid_offsets() -> Secp256k1SignatureOffsets {
+        Secp256k1SignatureOffsets {
+            signature_offset: 32,
+            signature_instruction_index: INDEX,
+            eth_address_offset: 12,
+            eth_address_instruction_index: INDEX,
+            message_data_offset: 97,
+            message_data_size: MESSAGE.len() as u16,
+            message_instruction_index: INDEX,
+        }
+    }
+
+    fn single(offsets: Secp256k1SignatureOffsets) -> Instruction {
+        let mut data = vec![1];
+        data.extend_from_slice(&offsets.try_to_vec().unwrap());
+        data.extend_from_slice(&ETH_ADDRESS);
+        data.extend_from_slice(&SIGNATURE);
+        data.extend_from_slice(MESSAGE);
+        Instruction { program_id: secp256k1_program::ID, accounts: vec![], data }
+    }
+
+    #[test]
+    fn parses_single_signature() {
+        let instruction = single(valid_offsets());
+        assert_eq!(
+            parse_instruction(&instruction, INDEX).unwrap(),
+            vec![Secp256k1Signature { eth_address: &ETH_ADDRESS, signature: &SIGNATURE[..64], recovery_id: 1, message: MESSAGE }]
+        );
+    }
+
+    #[test]
+    fn prefixes_personal_sign_messages() {
+        assert_eq!(personal_sign_message(b"Some data"), b"\x19Ethereum Signed Message:\n9Some data".to_vec());
+        let message = [0; 104];
+        let prefixed = personal_sign_message(&message);
+        assert_eq!(&prefixed[..PERSONAL_SIGN_PREFIX.len() + 3], b"\x19Ethereum Signed Message:\n104");
+        assert_eq!(&prefixed[PERSONAL_SIGN_PREFIX.len() + 3..], &message[..]);
+    }
+
+    #[test]
+    fn rejects_other_programs() {
+        let mut instruction = single(valid_offsets());
+        instruction.program_id = Pubkey::new_unique();
+        assert_eq!(parse_instruction(&instruction, INDEX), Err(Secp256k1Error::NotSecp256k1Program));
+    }
+
+    #[test]
+    fn rejects_short_or_empty_headers() {
+        let mut instruction = single(valid_offsets());
+        instruction.data = vec![];
+        assert_eq!(parse_instruction(&instruction, INDEX), Err(Secp256k1Error::Truncated));
+        instruction.data = vec![2, 32, 0, INDEX];
+        assert_eq!(parse_instruction(&instruction, INDEX), Err(Secp256k1Error::Truncated));
+        instruction.data = vec![0];
+        assert_eq!(parse_instruction(&instruction, INDEX), Err(Secp256k1Error::NoSignatures));
+    }
+
+    #[test]
+    fn rejects_foreign_instruction_indexes() {
+        let cases = [
+            Secp256k1SignatureOffsets { signature_instruction_index: 0, ..valid_offsets() },
+            Secp256k1SignatureOffsets { eth_address_instruction_index: INDEX + 1, ..valid_offsets() },
+            Secp256k1SignatureOffsets { message_instruction_index: u8::MAX, ..valid_offsets() },
+        ];
+        for offsets in cases {
+            assert_eq!(parse_instruction(&single(offsets), INDEX), Err(Secp256k1Error::ForeignInstructionIndex));
+        }
+    }
+
+    #[test]
+    fn rejects_offsets_past_the_data() {
+        let len = single(valid_offsets()).data.len() as u16;
+        let cases = [
+            Secp256k1SignatureOffsets { eth_address_offset: len - 19, ..valid_offsets() },
+            // the recovery id byte after the signature must be in bounds too
+            Secp256k1SignatureOffsets { signature_offset: len - 64, ..valid_offsets() },
+            Secp256k1SignatureOffsets { message_data_offset: u16::MAX, ..valid_offsets() },
+            Secp256k1SignatureOffsets { message_data_size: u16::MAX, ..valid_offsets() },
+        ];
+        for offsets in cases {
+            assert_eq!(parse_instruction(&single(offsets), INDEX), Err(Secp256k1Error::OffsetOutOfBounds));
+        }
+    }
+}
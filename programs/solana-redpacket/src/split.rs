@@ -0,0 +1,228 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::*, CustomError, RandomSplit, ShareTier, SplitMode, WeightTier};
+
+pub fn validate_split_mode(total_number: u32, total_amount: u64, split_mode: &SplitMode) -> Result<()> {
+    match split_mode {
+        SplitMode::Equal => Ok(()),
+        SplitMode::Random { split } => validate_random_split(total_number, total_amount, split),
+        SplitMode::FixedList { tiers, .. } => {
+            validate_tier_counts(total_number, tiers.iter().map(|tier| tier.count))?;
+            require!(tiers.iter().all(|tier| tier.amount > 0), CustomError::InvalidShareTiers);
+            let sum: u128 = tiers.iter().map(|tier| tier.count as u128 * tier.amount as u128).sum();
+            require!(sum == total_amount as u128, CustomError::InvalidShareTiers);
+            Ok(())
+        }
+        SplitMode::WeightedTiers { tiers, .. } => {
+            validate_tier_counts(total_number, tiers.iter().map(|tier| tier.count))?;
+            require!(tiers.iter().all(|tier| weighted_amount(total_amount, tier) > 0), CustomError::InvalidShareTiers);
+            // rounding dust from the weights is paid out with the last claim
+            let sum: u128 = tiers.iter().map(|tier| tier.count as u128 * tier.weight_bps as u128).sum();
+            require!(sum == SPLIT_WEIGHT_TOTAL_BPS as u128, CustomError::InvalidShareTiers);
+            Ok(())
+        }
+    }
+}
+
+// A top-up only has to keep the unclaimed part of the packet splittable. Tier lists are
+// fixed at creation, so they cannot be topped up.
+pub fn validate_top_up(remaining_number: u32, remaining_amount: u64, split_mode: &SplitMode) -> Result<()> {
+    match split_mode {
+        SplitMode::Equal => Ok(()),
+        SplitMode::Random { split } => validate_random_split(remaining_number, remaining_amount, split),
+        SplitMode::FixedList { .. } | SplitMode::WeightedTiers { .. } => err!(CustomError::InvalidSplitConfig),
+    }
+}
+
+fn validate_tier_counts(total_number: u32, tier_counts: impl ExactSizeIterator<Item = u32>) -> Result<()> {
+    require!(tier_counts.len() > 0 && tier_counts.len() <= RED_PACKET_MAX_SHARE_TIERS as usize, CustomError::InvalidShareTiers);
+    let mut sum: u64 = 0;
+    for count in tier_counts {
+        require!(count > 0, CustomError::InvalidShareTiers);
+        sum += count as u64;
+    }
+    require!(sum == total_number as u64, CustomError::InvalidShareTiers);
+    Ok(())
+}
+
+fn weighted_amount(total_amount: u64, tier: &WeightTier) -> u64 {
+    (total_amount as u128 * tier.weight_bps as u128 / SPLIT_WEIGHT_TOTAL_BPS as u128) as u64
+}
+
+// Index of the tier holding the pick-th unclaimed share, counting tiers in order
+fn pick_tier(unclaimed: impl Iterator<Item = u32>, pick: u64) -> usize {
+    let mut pick = pick;
+    for (index, left) in unclaimed.enumerate() {
+        if pick < left as u64 {
+            return index;
+        }
+        pick -= left as u64;
+    }
+    0
+}
+
+// Takes one share from the tier holding the pick-th unclaimed share and returns its amount
+pub fn take_tier_share(split_mode: &mut SplitMode, total_amount: u64, pick: u64) -> u64 {
+    match split_mode {
+        SplitMode::FixedList { tiers, .. } => {
+            let index = pick_tier(tiers.iter().map(|tier| tier.count - tier.claimed), pick);
+            let tier: &mut ShareTier = &mut tiers[index];
+            tier.claimed += 1;
+            tier.amount
+        }
+        SplitMode::WeightedTiers { tiers, .. } => {
+            let index = pick_tier(tiers.iter().map(|tier| tier.count - tier.claimed), pick);
+            let tier: &mut WeightTier = &mut tiers[index];
+            tier.claimed += 1;
+            weighted_amount(total_amount, tier)
+        }
+        _ => 0,
+    }
+}
+
+// Fixed amounts from MerkleAllowlist leaves bypass the split. Double-average bounds and tier
+// lists account for every share up front, so a fixed amount taken out of them could leave the
+// remaining claimers below min_share or drain a tier's funds.
+pub fn accepts_fixed_amounts(split_mode: &SplitMode) -> bool {
+    match split_mode {
+        SplitMode::Equal | SplitMode::Random { split: RandomSplit::Legacy } => true,
+        SplitMode::Random { split: RandomSplit::DoubleAverage { .. } } | SplitMode::FixedList { .. } | SplitMode::WeightedTiers { .. } => false,
+    }
+}
+
+// Every claimer must be able to get min_share, and max_share (when set) must be able to
+// absorb the whole amount, otherwise the last claimer would break one of the bounds.
+fn validate_random_split(total_number: u32, total_amount: u64, random_split: &RandomSplit) -> Result<()> {
+    if let RandomSplit::DoubleAverage { min_share, max_share } = *random_split {
+        require!(min_share > 0, CustomError::InvalidSplitConfig);
+        let total_number = total_number as u128;
+        require!(total_number * min_share as u128 <= total_amount as u128, CustomError::InvalidSplitConfig);
+        if max_share > 0 {
+            require!(max_share >= min_share, CustomError::InvalidSplitConfig);
+            require!(total_number * max_share as u128 >= total_amount as u128, CustomError::InvalidSplitConfig);
+        }
+    }
+    Ok(())
+}
+
+// Classic double-average: on top of min_share, draw uniformly from [0, 2 * average excess).
+// The result is clamped so the claimers left after this one can still each get a share
+// within [min_share, max_share], which keeps the final claim (the remainder) in bounds too.
+pub fn double_average_share(remaining_amount: u64, remaining_number: u64, min_share: u64, max_share: u64, random_value: u64) -> u64 {
+    let remaining = remaining_amount as u128;
+    let left = remaining_number as u128;
+    let min_share = min_share as u128;
+
+    let excess = remaining.saturating_sub(left * min_share);
+    let bound = excess * 2 / left;
+    let share = min_share + if bound == 0 { 0 } else { random_value as u128 % bound };
+
+    let mut lower = min_share;
+    let mut upper = remaining.saturating_sub((left - 1) * min_share);
+    if max_share > 0 {
+        let max_share = max_share as u128;
+        lower = lower.max(remaining.saturating_sub((left - 1) * max_share));
+        upper = upper.min(max_share);
+    }
+    let share = share.max(lower).min(upper);
+    share as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // splitmix64, enough to drive the split with varied inputs
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+    }
+
+    // Claims the whole packet the way calculate_claim_amount does: the last claimer takes the remainder
+    fn claim_all(total_number: u64, total_amount: u64, min_share: u64, max_share: u64, random: &mut Random) -> Vec<u64> {
+        let mut remaining = total_amount;
+        let shares: Vec<u64> = (0..total_number)
+            .map(|claimed| {
+                let left = total_number - claimed;
+                let share = if left == 1 { remaining } else { double_average_share(remaining, left, min_share, max_share, random.next()) };
+                remaining -= share;
+                share
+            })
+            .collect();
+        assert_eq!(remaining, 0);
+        shares
+    }
+
+    fn assert_shares_in_bounds(shares: &[u64], total_amount: u64, min_share: u64, max_share: u64) {
+        assert_eq!(shares.iter().sum::<u64>(), total_amount);
+        for &share in shares {
+            assert!(share >= min_share, "share {share} below min {min_share}");
+            assert!(max_share == 0 || share <= max_share, "share {share} above max {max_share}");
+        }
+    }
+
+    #[test]
+    fn double_average_shares_stay_in_bounds() {
+        let mut random = Random(7);
+        for _ in 0..5_000 {
+            let total_number = 1 + random.below(RED_PACKET_MAX_CLAIMERS as u64);
+            let min_share = 1 + random.below(1_000);
+            let total_amount = total_number * min_share + random.below(1_000_000);
+            // any max_share that validate_random_split accepts, or none
+            let max_share = match random.below(3) {
+                0 => 0,
+                _ => total_amount.div_ceil(total_number).max(min_share) + random.below(10_000),
+            };
+            let split = RandomSplit::DoubleAverage { min_share, max_share };
+            assert!(validate_random_split(total_number as u32, total_amount, &split).is_ok());
+
+            let shares = claim_all(total_number, total_amount, min_share, max_share, &mut random);
+            assert_shares_in_bounds(&shares, total_amount, min_share, max_share);
+        }
+    }
+
+    #[test]
+    fn double_average_shares_stay_in_bounds_on_tight_configs() {
+        let mut random = Random(11);
+        for _ in 0..5_000 {
+            let total_number = 1 + random.below(50);
+            let min_share = 1 + random.below(100);
+            // total_amount at or just above total_number * min_share, max_share at or just above the average
+            let total_amount = total_number * min_share + random.below(total_number);
+            let max_share = total_amount.div_ceil(total_number) + random.below(2);
+            let shares = claim_all(total_number, total_amount, min_share, max_share, &mut random);
+            assert_shares_in_bounds(&shares, total_amount, min_share, max_share);
+        }
+    }
+
+    #[test]
+    fn double_average_handles_extreme_random_values() {
+        for random_value in [0, 1, u64::MAX - 1, u64::MAX] {
+            let share = double_average_share(u64::MAX, 2, 1, 0, random_value);
+            assert!((1..u64::MAX).contains(&share));
+            assert_eq!(double_average_share(30, 3, 10, 10, random_value), 10);
+        }
+    }
+
+    #[test]
+    fn only_equal_and_legacy_splits_accept_fixed_amounts() {
+        assert!(accepts_fixed_amounts(&SplitMode::Equal));
+        assert!(accepts_fixed_amounts(&SplitMode::Random { split: RandomSplit::Legacy }));
+        assert!(!accepts_fixed_amounts(&SplitMode::Random { split: RandomSplit::DoubleAverage { min_share: 1, max_share: 0 } }));
+        let tiers = vec![ShareTier { count: 1, amount: 1, claimed: 0 }];
+        assert!(!accepts_fixed_amounts(&SplitMode::FixedList { tiers, shuffled: false }));
+        let tiers = vec![WeightTier { count: 1, weight_bps: SPLIT_WEIGHT_TOTAL_BPS, claimed: 0 }];
+        assert!(!accepts_fixed_amounts(&SplitMode::WeightedTiers { tiers, shuffled: true }));
+    }
+}
@@ -0,0 +1,114 @@
+//! Local HTTP front end for the claim signature service.
+//!
+//!   GET  /pubkey  -> {"pubkey": "<base58>"}
+//!   POST /sign    {"red_packet", "claimer", "recipient"} -> SignedClaimResponse, or 403 {"error"}
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use redpacket_signer::{
+    AllowlistPolicy, ClaimRequest, ClaimSignatureService, OnePerClaimerPolicy, RateLimitPolicy, Rejection,
+    SignedClaimResponse,
+};
+use serde_json::json;
+use solana_sdk::signature::read_keypair_file;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Parser)]
+#[command(about = "Issues claim signatures for redpacket claims")]
+struct Args {
+    /// Keypair file of the key registered as pubkey_for_claim_signature
+    #[arg(long)]
+    keypair: PathBuf,
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Only sign for claimers in this file (one base58 pubkey per line)
+    #[arg(long)]
+    allowlist: Option<PathBuf>,
+    /// Maximum signatures per claimer within --rate-limit-window seconds
+    #[arg(long)]
+    rate_limit: Option<usize>,
+    #[arg(long, default_value_t = 60)]
+    rate_limit_window: u64,
+    /// Allow more than one signature per claimer and red packet
+    #[arg(long)]
+    allow_repeat_claims: bool,
+    /// Issue versioned (SignatureV1) claim messages that expire this many seconds after signing
+    #[arg(long)]
+    signature_validity: Option<u64>,
+    /// Maximum claim amount covered by versioned claim messages, 0 for no cap
+    #[arg(long, default_value_t = 0, requires = "signature_validity")]
+    max_amount: u64,
+    /// Directory the rate limit and one-per-claimer state is kept in across restarts
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let keypair = read_keypair_file(&args.keypair)?;
+    let mut service = ClaimSignatureService::new(keypair);
+    if let Some(validity_secs) = args.signature_validity {
+        service = service.with_claim_message_v1(validity_secs, args.max_amount);
+    }
+    if let Some(path) = &args.allowlist {
+        service = service.with_policy(AllowlistPolicy::from_file(path)?);
+    }
+    if let Some(dir) = &args.state_dir {
+        fs::create_dir_all(dir)?;
+    }
+    if let Some(max_requests) = args.rate_limit {
+        let mut policy = RateLimitPolicy::new(max_requests, args.rate_limit_window);
+        if let Some(dir) = &args.state_dir {
+            policy = policy.with_state_file(dir.join("rate-limit.jsonl"), unix_now())?;
+        }
+        service = service.with_policy(policy);
+    }
+    if !args.allow_repeat_claims {
+        let mut policy = OnePerClaimerPolicy::new();
+        if let Some(dir) = &args.state_dir {
+            policy = policy.with_state_file(dir.join("one-per-claimer.jsonl"), unix_now())?;
+        }
+        service = service.with_policy(policy);
+    }
+
+    let server = Server::http(&args.listen).map_err(|err| err.to_string())?;
+    println!("signing claims with {} on {}", service.pubkey(), args.listen);
+    for mut request in server.incoming_requests() {
+        let (status, body) = handle(&mut service, &mut request);
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+        if let Err(err) = request.respond(response) {
+            eprintln!("failed to respond: {err}");
+        }
+    }
+    Ok(())
+}
+
+fn handle(service: &mut ClaimSignatureService, request: &mut Request) -> (u16, serde_json::Value) {
+    match (request.method(), request.url()) {
+        (Method::Get, "/pubkey") => (200, json!({ "pubkey": service.pubkey().to_string() })),
+        (Method::Post, "/sign") => {
+            let claim_request: ClaimRequest = match serde_json::from_reader(request.as_reader()) {
+                Ok(claim_request) => claim_request,
+                Err(err) => return (400, json!({ "error": err.to_string() })),
+            };
+            match service.issue(&claim_request, unix_now()) {
+                Ok(claim) => (200, json!(SignedClaimResponse::from(&claim))),
+                Err(rejection @ Rejection::StateNotSaved(_)) => (500, json!({ "error": rejection.to_string() })),
+                Err(rejection) => (403, json!({ "error": rejection.to_string() })),
+            }
+        }
+        _ => (404, json!({ "error": "not found" })),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
@@ -0,0 +1,10 @@
+//! Issues the Ed25519 claim signatures checked by the redpacket program. The service holds the
+//! keypair registered as `pubkey_for_claim_signature`, runs every request through a list of
+//! eligibility policies and returns the signature together with the verify instruction that has
+//! to precede the claim. Time is passed in by the caller, so everything runs offline.
+
+pub mod policy;
+pub mod service;
+
+pub use policy::*;
+pub use service::*;
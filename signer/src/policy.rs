@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+use crate::{service::base58_pubkey, ClaimRequest};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Rejection {
+    #[error("claimer {0} is not on the allowlist")]
+    NotAllowlisted(Pubkey),
+    #[error("claimer {0} is over the rate limit")]
+    RateLimited(Pubkey),
+    #[error("claimer {claimer} already has a signature for red packet {red_packet}")]
+    AlreadySigned { red_packet: Pubkey, claimer: Pubkey },
+    #[error("failed to save the policy state: {0}")]
+    StateNotSaved(String),
+}
+
+// Decides whether a claim request gets a signature. check must not change any state;
+// record is called once every policy accepted the request, before it is signed, with the
+// deadline of the signature (None for legacy messages, which never expire).
+pub trait EligibilityPolicy: Send {
+    fn check(&self, request: &ClaimRequest, now: u64) -> Result<(), Rejection>;
+
+    fn record(&mut self, _request: &ClaimRequest, _now: u64, _deadline: Option<u64>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// JSON lines file a policy appends every recorded request to and replays on start,
+// so its state survives restarts of the service
+struct StateFile {
+    file: File,
+}
+
+impl StateFile {
+    // Reads the entries saved at path (none if it does not exist yet), then rewrites the file
+    // with the ones keep accepts
+    fn open<T: Serialize + DeserializeOwned>(
+        path: &Path,
+        keep: impl Fn(&T) -> bool,
+    ) -> io::Result<(Self, Vec<T>)> {
+        let mut entries = vec![];
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry: T = serde_json::from_str(&line)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {err}", path.display())))?;
+                if keep(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        let mut compacted = path.as_os_str().to_owned();
+        compacted.push(".tmp");
+        let compacted = PathBuf::from(compacted);
+        let mut state_file = StateFile { file: File::create(&compacted)? };
+        for entry in &entries {
+            state_file.append(entry)?;
+        }
+        fs::rename(&compacted, path)?;
+        state_file.file = OpenOptions::new().append(true).open(path)?;
+        Ok((state_file, entries))
+    }
+
+    fn append<T: Serialize>(&mut self, entry: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+}
+
+// Only claimers listed in the allowlist are signed for
+pub struct AllowlistPolicy {
+    claimers: HashSet<Pubkey>,
+}
+
+impl AllowlistPolicy {
+    pub fn new(claimers: impl IntoIterator<Item = Pubkey>) -> Self {
+        AllowlistPolicy { claimers: claimers.into_iter().collect() }
+    }
+
+    // One base58 public key per line, blank lines and lines starting with # are skipped
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut claimers = HashSet::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let claimer = Pubkey::from_str(line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{line}: {err}")))?;
+            claimers.insert(claimer);
+        }
+        Ok(AllowlistPolicy { claimers })
+    }
+}
+
+impl EligibilityPolicy for AllowlistPolicy {
+    fn check(&self, request: &ClaimRequest, _now: u64) -> Result<(), Rejection> {
+        if self.claimers.contains(&request.claimer) {
+            Ok(())
+        } else {
+            Err(Rejection::NotAllowlisted(request.claimer))
+        }
+    }
+}
+
+// At most max_requests signatures per claimer within any window_secs long window
+pub struct RateLimitPolicy {
+    max_requests: usize,
+    window_secs: u64,
+    issued: HashMap<Pubkey, VecDeque<u64>>,
+    state: Option<StateFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IssuedAt {
+    #[serde(with = "base58_pubkey")]
+    claimer: Pubkey,
+    time: u64,
+}
+
+impl RateLimitPolicy {
+    pub fn new(max_requests: usize, window_secs: u64) -> Self {
+        RateLimitPolicy { max_requests, window_secs, issued: HashMap::new(), state: None }
+    }
+
+    // Keeps the issue times in path, dropping the ones already out of the window at now
+    pub fn with_state_file(mut self, path: impl AsRef<Path>, now: u64) -> io::Result<Self> {
+        let window_secs = self.window_secs;
+        let (state, entries) = StateFile::open(path.as_ref(), |entry: &IssuedAt| entry.time + window_secs > now)?;
+        for entry in entries {
+            self.issued.entry(entry.claimer).or_default().push_back(entry.time);
+        }
+        self.state = Some(state);
+        Ok(self)
+    }
+
+    fn recent(&self, claimer: &Pubkey, now: u64) -> usize {
+        self.issued
+            .get(claimer)
+            .map_or(0, |times| times.iter().filter(|&&time| time + self.window_secs > now).count())
+    }
+}
+
+impl EligibilityPolicy for RateLimitPolicy {
+    fn check(&self, request: &ClaimRequest, now: u64) -> Result<(), Rejection> {
+        if self.recent(&request.claimer, now) < self.max_requests {
+            Ok(())
+        } else {
+            Err(Rejection::RateLimited(request.claimer))
+        }
+    }
+
+    fn record(&mut self, request: &ClaimRequest, now: u64, _deadline: Option<u64>) -> io::Result<()> {
+        if let Some(state) = &mut self.state {
+            state.append(&IssuedAt { claimer: request.claimer, time: now })?;
+        }
+        let window_secs = self.window_secs;
+        let times = self.issued.entry(request.claimer).or_default();
+        while times.front().is_some_and(|&time| time + window_secs <= now) {
+            times.pop_front();
+        }
+        times.push_back(now);
+        Ok(())
+    }
+}
+
+// One live signature per claimer and red packet, whatever the recipient. A claimer whose
+// signature passed its deadline unused is signed for again; legacy messages never expire.
+#[derive(Default)]
+pub struct OnePerClaimerPolicy {
+    // deadline of the last signature per (red packet, claimer)
+    signed: HashMap<(Pubkey, Pubkey), Option<u64>>,
+    state: Option<StateFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignedFor {
+    #[serde(with = "base58_pubkey")]
+    red_packet: Pubkey,
+    #[serde(with = "base58_pubkey")]
+    claimer: Pubkey,
+    deadline: Option<u64>,
+}
+
+impl OnePerClaimerPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Keeps the signed claimers in path, dropping the signatures already expired at now
+    pub fn with_state_file(mut self, path: impl AsRef<Path>, now: u64) -> io::Result<Self> {
+        let (state, entries) =
+            StateFile::open(path.as_ref(), |entry: &SignedFor| entry.deadline.is_none_or(|deadline| deadline >= now))?;
+        for entry in entries {
+            self.signed.insert((entry.red_packet, entry.claimer), entry.deadline);
+        }
+        self.state = Some(state);
+        Ok(self)
+    }
+}
+
+impl EligibilityPolicy for OnePerClaimerPolicy {
+    fn check(&self, request: &ClaimRequest, now: u64) -> Result<(), Rejection> {
+        match self.signed.get(&(request.red_packet, request.claimer)) {
+            // the program rejects signatures after their deadline, so the old one is spent
+            Some(Some(deadline)) if *deadline < now => Ok(()),
+            Some(_) => Err(Rejection::AlreadySigned { red_packet: request.red_packet, claimer: request.claimer }),
+            None => Ok(()),
+        }
+    }
+
+    fn record(&mut self, request: &ClaimRequest, _now: u64, deadline: Option<u64>) -> io::Result<()> {
+        if let Some(state) = &mut self.state {
+            state.append(&SignedFor { red_packet: request.red_packet, claimer: request.claimer, deadline })?;
+        }
+        self.signed.insert((request.red_packet, request.claimer), deadline);
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use redpacket_client::{claim_message, claim_message_v1, ed25519_verify_instruction, ClaimSignature, ClaimTerms};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+
+use crate::policy::{EligibilityPolicy, Rejection};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimRequest {
+    #[serde(with = "base58_pubkey")]
+    pub red_packet: Pubkey,
+    #[serde(with = "base58_pubkey")]
+    pub claimer: Pubkey,
+    #[serde(with = "base58_pubkey")]
+    pub recipient: Pubkey,
+}
+
+pub struct SignedClaim {
+    pub signer: Pubkey,
+    pub message: Vec<u8>,
+    pub signature: Signature,
+    // deadline and max amount for ClaimAuthorization::SignatureV1, None for legacy messages
+    pub terms: Option<ClaimTerms>,
+    // Ed25519 verify instruction to put right before the claim instruction
+    pub instruction: Instruction,
+}
+
+// JSON form of a SignedClaim, keys base58 and byte strings base64
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedClaimResponse {
+    pub signer: String,
+    pub message: String,
+    pub signature: String,
+    pub instruction_program_id: String,
+    pub instruction_data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<u64>,
+}
+
+impl SignedClaim {
+    // What claim_red_packet_with_signature takes
+    pub fn claim_signature(&self) -> ClaimSignature {
+        ClaimSignature { signature: self.signature.into(), terms: self.terms }
+    }
+}
+
+impl From<&SignedClaim> for SignedClaimResponse {
+    fn from(claim: &SignedClaim) -> Self {
+        SignedClaimResponse {
+            signer: claim.signer.to_string(),
+            message: STANDARD.encode(&claim.message),
+            signature: claim.signature.to_string(),
+            instruction_program_id: claim.instruction.program_id.to_string(),
+            instruction_data: STANDARD.encode(&claim.instruction.data),
+            deadline: claim.terms.map(|terms| terms.deadline),
+            max_amount: claim.terms.map(|terms| terms.max_amount),
+        }
+    }
+}
+
+pub struct ClaimSignatureService {
+    keypair: Keypair,
+    policies: Vec<Box<dyn EligibilityPolicy>>,
+    // (validity in seconds, max amount) of ClaimAuthorization::SignatureV1 messages
+    message_v1: Option<(u64, u64)>,
+}
+
+impl ClaimSignatureService {
+    pub fn new(keypair: Keypair) -> Self {
+        ClaimSignatureService { keypair, policies: vec![], message_v1: None }
+    }
+
+    // Sign ClaimAuthorization::SignatureV1 messages that expire validity_secs after issuing and
+    // cap the claim at max_amount (0 for no cap), instead of legacy messages
+    pub fn with_claim_message_v1(mut self, validity_secs: u64, max_amount: u64) -> Self {
+        self.message_v1 = Some((validity_secs, max_amount));
+        self
+    }
+
+    pub fn with_policy(mut self, policy: impl EligibilityPolicy + 'static) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+
+    // The key red packets have to be created with as pubkey_for_claim_signature
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    // Signs the claim message once every policy accepts the request
+    pub fn issue(&mut self, request: &ClaimRequest, now: u64) -> Result<SignedClaim, Rejection> {
+        for policy in &self.policies {
+            policy.check(request, now)?;
+        }
+        let terms = self
+            .message_v1
+            .map(|(validity_secs, max_amount)| ClaimTerms { deadline: now + validity_secs, max_amount });
+        for policy in &mut self.policies {
+            policy
+                .record(request, now, terms.map(|terms| terms.deadline))
+                .map_err(|err| Rejection::StateNotSaved(err.to_string()))?;
+        }
+
+        let message = match &terms {
+            Some(terms) => claim_message_v1(&request.red_packet, &request.claimer, &request.recipient, terms),
+            None => claim_message(&request.red_packet, &request.claimer, &request.recipient),
+        };
+        let signature = self.keypair.sign_message(&message);
+        let signature_bytes: [u8; 64] = signature.into();
+        let instruction = ed25519_verify_instruction(&self.pubkey(), &message, &signature_bytes);
+        Ok(SignedClaim { signer: self.pubkey(), message, signature, terms, instruction })
+    }
+}
+
+pub(crate) mod base58_pubkey {
+    use std::str::FromStr;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&pubkey.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Pubkey::from_str(&value).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,131 @@
+use redpacket_client::{claim_message, claim_message_v1, ClaimProof, ClaimTerms};
+use redpacket_signer::{
+    AllowlistPolicy, ClaimRequest, ClaimSignatureService, OnePerClaimerPolicy, RateLimitPolicy, Rejection,
+};
+use solana_sdk::{ed25519_program, pubkey::Pubkey, signature::Keypair};
+
+fn request(claimer: Pubkey) -> ClaimRequest {
+    ClaimRequest { red_packet: Pubkey::new_unique(), claimer, recipient: Pubkey::new_unique() }
+}
+
+#[test]
+fn issues_a_verifiable_ed25519_instruction() {
+    let mut service = ClaimSignatureService::new(Keypair::new());
+    let request = request(Pubkey::new_unique());
+
+    let claim = service.issue(&request, 0).unwrap();
+    let message = claim_message(&request.red_packet, &request.claimer, &request.recipient);
+    assert_eq!(claim.message, message);
+    assert!(claim.signature.verify(service.pubkey().as_ref(), &message));
+
+    let data = &claim.instruction.data;
+    assert_eq!(claim.instruction.program_id, ed25519_program::ID);
+    assert_eq!(&data[16..48], service.pubkey().as_ref());
+    assert_eq!(&data[48..112], claim.signature.as_ref());
+    assert_eq!(&data[112..], message.as_slice());
+}
+
+#[test]
+fn issues_expiring_versioned_messages() {
+    let mut service = ClaimSignatureService::new(Keypair::new()).with_claim_message_v1(300, 50);
+    let request = request(Pubkey::new_unique());
+
+    let claim = service.issue(&request, 1_000).unwrap();
+    let terms = ClaimTerms { deadline: 1_300, max_amount: 50 };
+    assert_eq!(claim.terms, Some(terms));
+    let message = claim_message_v1(&request.red_packet, &request.claimer, &request.recipient, &terms);
+    assert_eq!(claim.message, message);
+    assert!(claim.signature.verify(service.pubkey().as_ref(), &message));
+    assert!(matches!(claim.claim_signature().proof(), ClaimProof::SignatureV1 { deadline: 1_300, max_amount: 50 }));
+}
+
+#[test]
+fn rejects_claimers_outside_the_allowlist() {
+    let allowed = Pubkey::new_unique();
+    let mut service = ClaimSignatureService::new(Keypair::new()).with_policy(AllowlistPolicy::new([allowed]));
+
+    assert!(service.issue(&request(allowed), 0).is_ok());
+    let stranger = Pubkey::new_unique();
+    assert_eq!(service.issue(&request(stranger), 0).err(), Some(Rejection::NotAllowlisted(stranger)));
+}
+
+#[test]
+fn rate_limit_window_slides() {
+    let claimer = Pubkey::new_unique();
+    let mut service = ClaimSignatureService::new(Keypair::new()).with_policy(RateLimitPolicy::new(2, 60));
+
+    assert!(service.issue(&request(claimer), 0).is_ok());
+    assert!(service.issue(&request(claimer), 30).is_ok());
+    assert_eq!(service.issue(&request(claimer), 59).err(), Some(Rejection::RateLimited(claimer)));
+    assert!(service.issue(&request(claimer), 60).is_ok());
+}
+
+#[test]
+fn signs_once_per_claimer_and_red_packet() {
+    let mut service = ClaimSignatureService::new(Keypair::new()).with_policy(OnePerClaimerPolicy::new());
+    let first = request(Pubkey::new_unique());
+    let other_recipient = ClaimRequest { recipient: Pubkey::new_unique(), ..first };
+
+    assert!(service.issue(&first, 0).is_ok());
+    assert_eq!(
+        service.issue(&other_recipient, 0).err(),
+        Some(Rejection::AlreadySigned { red_packet: first.red_packet, claimer: first.claimer })
+    );
+    // the claimer can still get a signature for another red packet
+    assert!(service.issue(&request(first.claimer), 0).is_ok());
+}
+
+#[test]
+fn rejected_requests_are_not_recorded() {
+    let claimer = Pubkey::new_unique();
+    let mut service = ClaimSignatureService::new(Keypair::new())
+        .with_policy(OnePerClaimerPolicy::new())
+        .with_policy(RateLimitPolicy::new(1, 60));
+
+    assert!(service.issue(&request(claimer), 0).is_ok());
+    let second = request(claimer);
+    assert_eq!(service.issue(&second, 10).err(), Some(Rejection::RateLimited(claimer)));
+    // the one-per-claimer policy did not count the rate limited request
+    assert!(service.issue(&second, 60).is_ok());
+}
+
+#[test]
+fn signs_again_once_the_previous_signature_expired() {
+    let mut service =
+        ClaimSignatureService::new(Keypair::new()).with_claim_message_v1(300, 0).with_policy(OnePerClaimerPolicy::new());
+    let first = request(Pubkey::new_unique());
+
+    assert!(service.issue(&first, 0).is_ok());
+    // the signature is still claimable at its deadline
+    assert_eq!(
+        service.issue(&first, 300).err(),
+        Some(Rejection::AlreadySigned { red_packet: first.red_packet, claimer: first.claimer })
+    );
+    let claim = service.issue(&first, 301).unwrap();
+    assert_eq!(claim.terms.map(|terms| terms.deadline), Some(601));
+}
+
+#[test]
+fn policy_state_survives_a_restart() {
+    let dir = std::env::temp_dir().join(format!("redpacket-signer-{}", Pubkey::new_unique()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let service = |now| {
+        ClaimSignatureService::new(Keypair::new())
+            .with_policy(OnePerClaimerPolicy::new().with_state_file(dir.join("one-per-claimer.jsonl"), now).unwrap())
+            .with_policy(RateLimitPolicy::new(2, 60).with_state_file(dir.join("rate-limit.jsonl"), now).unwrap())
+    };
+    let first = request(Pubkey::new_unique());
+
+    assert!(service(0).issue(&first, 0).is_ok());
+    let mut restarted = service(10);
+    assert_eq!(
+        restarted.issue(&first, 10).err(),
+        Some(Rejection::AlreadySigned { red_packet: first.red_packet, claimer: first.claimer })
+    );
+    assert!(restarted.issue(&request(first.claimer), 20).is_ok());
+    assert_eq!(restarted.issue(&request(first.claimer), 30).err(), Some(Rejection::RateLimited(first.claimer)));
+    // times out of the window are dropped when the state is loaded
+    assert!(service(80).issue(&request(first.claimer), 80).is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,235 @@
+//! Compute units, account sizes and rent of create, claim and withdraw across packet sizes,
+//! printed as a Markdown table, or JSON with `--json`. Needs target/deploy/redpacket.so from
+//! `anchor build`: `RUST_LOG=error cargo run --release -p redpacket-svm-tests --bin cost-report [-- --json]`
+
+use anchor_spl::token;
+use redpacket_client::{
+    claim_red_packet_with_signature, claim_receipt_address, create_red_packet, red_packet_address,
+    redpacket::constants::RED_PACKET_MAX_CLAIMERS, vault_address, withdraw_red_packet, Asset, RandomSplit, SplitModeInput,
+};
+use redpacket_svm_tests::*;
+use serde_json::json;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+// Claims recorded in the red packet account are capped at RED_PACKET_MAX_CLAIMERS,
+// larger packets need claim receipts
+const SIZES_IN_ACCOUNT: [u32; 3] = [1, 50, RED_PACKET_MAX_CLAIMERS as u32];
+const SIZES_WITH_RECEIPTS: [u32; 3] = [1, 50, 250];
+const SHARE: u64 = 1_000_000;
+
+struct Scenario {
+    native: bool,
+    random: bool,
+    claim_receipts: bool,
+    claimers: u32,
+}
+
+struct Costs {
+    create_units: u64,
+    claim_units: Vec<u64>,
+    withdraw_units: u64,
+    red_packet_size: usize,
+    red_packet_rent: u64,
+    claim_receipt_size: usize,
+    claim_receipt_rent: u64,
+    vault_rent: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    let json = std::env::args().any(|arg| arg == "--json");
+    if !program_is_compiled() {
+        eprintln!("target/deploy/redpacket.so not found, run `anchor build` first");
+        std::process::exit(1);
+    }
+    let mut report = Vec::new();
+    for native in [true, false] {
+        for random in [false, true] {
+            for (claim_receipts, sizes) in [(false, SIZES_IN_ACCOUNT), (true, SIZES_WITH_RECEIPTS)] {
+                for claimers in sizes {
+                    let scenario = Scenario { native, random, claim_receipts, claimers };
+                    let costs = run(&scenario).await;
+                    report.push((scenario, costs));
+                }
+            }
+        }
+    }
+    if json {
+        print_json(&report);
+    } else {
+        print_markdown(&report);
+    }
+}
+
+// Creates a packet on a fresh bank, claims every share with a new claimer, then withdraws
+async fn run(scenario: &Scenario) -> Costs {
+    let mut context = start().await.expect("compiled program");
+    let creator = funded_keypair(&mut context, 100 * LAMPORTS_PER_SOL).await;
+    let claim_signer = Keypair::new();
+    let total_amount = scenario.claimers as u64 * SHARE;
+    let asset = if scenario.native {
+        Asset::Native
+    } else {
+        let mint = create_mint(&mut context, &token::ID).await;
+        mint_to(&mut context, &token::ID, &mint, &creator.pubkey(), total_amount).await;
+        Asset::SplToken { mint, token_program: token::ID }
+    };
+
+    let mut params = create_params(&claim_signer.pubkey(), scenario.claimers, total_amount, 60 * 60);
+    if scenario.random {
+        params.split_mode = SplitModeInput::Random { split: RandomSplit::Legacy };
+    }
+    params.use_claim_receipts = scenario.claim_receipts;
+    let create_time = now(&mut context).await;
+    let create = create_red_packet(&creator.pubkey(), create_time, &asset, params);
+    let create_units = send_metered(&mut context, &[create], &[&creator]).await.unwrap();
+    let red_packet = red_packet_address(&creator.pubkey(), create_time).0;
+    let account = fetch_red_packet(&mut context, &red_packet).await;
+
+    let mut claim_units = Vec::new();
+    let mut claim_receipt_size = 0;
+    for _ in 0..scenario.claimers {
+        let claimer = funded_keypair(&mut context, LAMPORTS_PER_SOL / 10).await;
+        let signature = sign_claim(&claim_signer, &red_packet, &claimer.pubkey(), &claimer.pubkey());
+        let claim = claim_red_packet_with_signature(
+            &red_packet,
+            &account,
+            &asset,
+            &claimer.pubkey(),
+            &claimer.pubkey(),
+            &signature,
+            false,
+        );
+        claim_units.push(send_metered(&mut context, &claim, &[&claimer]).await.unwrap());
+        if scenario.claim_receipts {
+            claim_receipt_size = account_size(&mut context, &claim_receipt_address(&red_packet, &claimer.pubkey()).0).await;
+        }
+    }
+
+    // sizes once every claim is recorded
+    let red_packet_size = account_size(&mut context, &red_packet).await;
+    let vault_size = match asset {
+        Asset::SplToken { mint, token_program } => {
+            account_size(&mut context, &vault_address(&red_packet, &mint, &token_program)).await
+        }
+        Asset::Native => 0,
+    };
+    let account = fetch_red_packet(&mut context, &red_packet).await;
+    let withdraw = withdraw_red_packet(&red_packet, &account, &asset);
+    let withdraw_units = send_metered(&mut context, &[withdraw], &[&creator]).await.unwrap();
+
+    Costs {
+        create_units,
+        claim_units,
+        withdraw_units,
+        red_packet_size,
+        red_packet_rent: rent_exempt_lamports(&mut context, red_packet_size).await,
+        claim_receipt_size,
+        claim_receipt_rent: if scenario.claim_receipts {
+            rent_exempt_lamports(&mut context, claim_receipt_size).await
+        } else {
+            0
+        },
+        vault_rent: if vault_size > 0 { rent_exempt_lamports(&mut context, vault_size).await } else { 0 },
+    }
+}
+
+impl Scenario {
+    fn asset(&self) -> &'static str {
+        if self.native {
+            "SOL"
+        } else {
+            "SPL"
+        }
+    }
+
+    fn split(&self) -> &'static str {
+        if self.random {
+            "random"
+        } else {
+            "equal"
+        }
+    }
+
+    fn claim_records(&self) -> &'static str {
+        if self.claim_receipts {
+            "receipts"
+        } else {
+            "account"
+        }
+    }
+}
+
+impl Costs {
+    fn claim_first(&self) -> u64 {
+        self.claim_units.first().copied().unwrap_or_default()
+    }
+
+    fn claim_last(&self) -> u64 {
+        self.claim_units.last().copied().unwrap_or_default()
+    }
+
+    fn claim_max(&self) -> u64 {
+        self.claim_units.iter().copied().max().unwrap_or_default()
+    }
+
+    fn claim_mean(&self) -> u64 {
+        self.claim_units.iter().sum::<u64>() / self.claim_units.len().max(1) as u64
+    }
+}
+
+fn print_markdown(report: &[(Scenario, Costs)]) {
+    println!(
+        "| asset | split | claim records | claimers | create CU | claim CU first / mean / last / max | withdraw CU \
+         | red packet bytes | red packet rent | claim receipt bytes | claim receipt rent | vault rent |"
+    );
+    println!("|---|---|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|");
+    for (scenario, costs) in report {
+        println!(
+            "| {} | {} | {} | {} | {} | {} / {} / {} / {} | {} | {} | {} | {} | {} | {} |",
+            scenario.asset(),
+            scenario.split(),
+            scenario.claim_records(),
+            scenario.claimers,
+            costs.create_units,
+            costs.claim_first(),
+            costs.claim_mean(),
+            costs.claim_last(),
+            costs.claim_max(),
+            costs.withdraw_units,
+            costs.red_packet_size,
+            costs.red_packet_rent,
+            costs.claim_receipt_size,
+            costs.claim_receipt_rent,
+            costs.vault_rent,
+        );
+    }
+}
+
+fn print_json(report: &[(Scenario, Costs)]) {
+    let rows: Vec<_> = report
+        .iter()
+        .map(|(scenario, costs)| {
+            json!({
+                "asset": scenario.asset(),
+                "split": scenario.split(),
+                "claim_records": scenario.claim_records(),
+                "claimers": scenario.claimers,
+                "create_compute_units": costs.create_units,
+                "claim_compute_units": {
+                    "first": costs.claim_first(),
+                    "mean": costs.claim_mean(),
+                    "last": costs.claim_last(),
+                    "max": costs.claim_max(),
+                },
+                "withdraw_compute_units": costs.withdraw_units,
+                "red_packet_bytes": costs.red_packet_size,
+                "red_packet_rent": costs.red_packet_rent,
+                "claim_receipt_bytes": costs.claim_receipt_size,
+                "claim_receipt_rent": costs.claim_receipt_rent,
+                "vault_rent": costs.vault_rent,
+            })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&rows).unwrap());
+}
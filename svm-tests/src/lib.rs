@@ -0,0 +1,201 @@
+//! Helpers for running the compiled redpacket program on an in-process SVM (solana-program-test):
+//! a bank with the program loaded, clock warping, token setup and claim signing.
+
+use std::io::Write;
+
+use anchor_lang::AccountDeserialize;
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account},
+    token_2022::spl_token_2022::{
+        self,
+        extension::StateWithExtensions,
+        state::{Account as TokenAccount, Mint},
+    },
+};
+use redpacket_client::{
+    claim_message, redpacket::CustomError, ClaimAuthorization, ClaimSignature, CreateParams, RandomnessSource,
+    RedPacket, SplitModeInput, PROGRAM_ID,
+};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+const DEPLOY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../target/deploy");
+
+// Starts a bank with the redpacket program loaded from target/deploy/redpacket.so, SPL Token,
+// Token-2022 and the associated token program are part of solana-program-test's genesis.
+// Without `anchor build` there is nothing to load: the test says so on stderr and is skipped.
+pub async fn start() -> Option<ProgramTestContext> {
+    if !program_is_compiled() {
+        // written to stderr directly, the test harness captures eprintln! of passing tests
+        let _ = writeln!(
+            std::io::stderr(),
+            "SKIPPED: redpacket.so not found in target/deploy, run `anchor build` to run the SVM tests"
+        );
+        return None;
+    }
+    if std::env::var("BPF_OUT_DIR").is_err() && std::env::var("SBF_OUT_DIR").is_err() {
+        std::env::set_var("SBF_OUT_DIR", DEPLOY_DIR);
+    }
+    let mut program_test = ProgramTest::new("redpacket", PROGRAM_ID, None);
+    program_test.prefer_bpf(true);
+    Some(program_test.start_with_context().await)
+}
+
+// Whether `anchor build` left the compiled program where start() loads it from
+pub fn program_is_compiled() -> bool {
+    let out_dir = std::env::var("SBF_OUT_DIR").or_else(|_| std::env::var("BPF_OUT_DIR")).unwrap_or(DEPLOY_DIR.into());
+    std::path::Path::new(&out_dir).join("redpacket.so").exists()
+}
+
+pub async fn now(context: &mut ProgramTestContext) -> u64 {
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp as u64
+}
+
+// Moves the Clock sysvar to unix_timestamp. The next transaction gets a fresh blockhash, so one
+// that failed before the warp is not rejected as already processed when sent again.
+pub async fn warp_to(context: &mut ProgramTestContext, unix_timestamp: u64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp as i64;
+    context.set_sysvar(&clock);
+    context.get_new_latest_blockhash().await.unwrap();
+}
+
+// Sends the instructions with the context payer paying the fees
+pub async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let transaction = transaction(context, instructions, signers);
+    context.banks_client.process_transaction(transaction).await
+}
+
+// Like send, returning the compute units the transaction consumed
+pub async fn send_metered(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<u64, BanksClientError> {
+    let transaction = transaction(context, instructions, signers);
+    let result = context.banks_client.process_transaction_with_metadata(transaction).await?;
+    result.result?;
+    Ok(result.metadata.map_or(0, |metadata| metadata.compute_units_consumed))
+}
+
+fn transaction(context: &ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> Transaction {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    Transaction::new_signed_with_payer(instructions, Some(&context.payer.pubkey()), &all_signers, context.last_blockhash)
+}
+
+// A new keypair holding lamports, to act as creator or claimer
+pub async fn funded_keypair(context: &mut ProgramTestContext, lamports: u64) -> Keypair {
+    let keypair = Keypair::new();
+    let transfer = system_instruction::transfer(&context.payer.pubkey(), &keypair.pubkey(), lamports);
+    send(context, &[transfer], &[]).await.unwrap();
+    keypair
+}
+
+pub async fn balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    context.banks_client.get_balance(*address).await.unwrap()
+}
+
+pub async fn account_size(context: &mut ProgramTestContext, address: &Pubkey) -> usize {
+    context.banks_client.get_account(*address).await.unwrap().map_or(0, |account| account.data.len())
+}
+
+pub async fn rent_exempt_lamports(context: &mut ProgramTestContext, size: usize) -> u64 {
+    context.banks_client.get_rent().await.unwrap().minimum_balance(size)
+}
+
+pub async fn account_exists(context: &mut ProgramTestContext, address: &Pubkey) -> bool {
+    context.banks_client.get_account(*address).await.unwrap().is_some()
+}
+
+pub async fn fetch_red_packet(context: &mut ProgramTestContext, address: &Pubkey) -> RedPacket {
+    let account = context.banks_client.get_account(*address).await.unwrap().expect("red packet account");
+    RedPacket::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+// A mint owned by token_program (SPL Token or Token-2022) with the context payer as authority
+pub async fn create_mint(context: &mut ProgramTestContext, token_program: &Pubkey) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(Mint::LEN);
+    let instructions = [
+        system_instruction::create_account(&context.payer.pubkey(), &mint.pubkey(), rent, Mint::LEN as u64, token_program),
+        spl_token_2022::instruction::initialize_mint2(token_program, &mint.pubkey(), &context.payer.pubkey(), None, 6)
+            .unwrap(),
+    ];
+    send(context, &instructions, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+// Mints amount into owner's associated token account, creating it if needed
+pub async fn mint_to(
+    context: &mut ProgramTestContext,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let token_account = get_associated_token_address_with_program_id(owner, mint, token_program);
+    let payer = context.payer.pubkey();
+    let instructions = [
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+            &payer,
+            owner,
+            mint,
+            token_program,
+        ),
+        spl_token_2022::instruction::mint_to(token_program, mint, &token_account, &payer, &[], amount).unwrap(),
+    ];
+    send(context, &instructions, &[]).await.unwrap();
+    token_account
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, token_account: &Pubkey) -> u64 {
+    let account = context.banks_client.get_account(*token_account).await.unwrap().expect("token account");
+    StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap().base.amount
+}
+
+// Signature-authorized, equal split parameters
+pub fn create_params(claim_signer: &Pubkey, total_number: u32, total_amount: u64, duration: u64) -> CreateParams {
+    CreateParams {
+        total_number,
+        total_amount,
+        duration,
+        open_time: 0,
+        split_mode: SplitModeInput::Equal,
+        pubkey_for_claim_signature: *claim_signer,
+        name: "svm test".to_string(),
+        message: "happy new year".to_string(),
+        use_claim_receipts: false,
+        claim_authorization: ClaimAuthorization::Signature,
+        randomness: RandomnessSource::Timestamp,
+    }
+}
+
+// What the claim signature service would return for this claim
+pub fn sign_claim(claim_signer: &Keypair, red_packet: &Pubkey, claimer: &Pubkey, recipient: &Pubkey) -> ClaimSignature {
+    let signature = claim_signer.sign_message(&claim_message(red_packet, claimer, recipient));
+    ClaimSignature { signature: signature.into(), terms: None }
+}
+
+pub fn assert_custom_error(result: Result<(), BanksClientError>, expected: CustomError) {
+    match result.expect_err("transaction should fail").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => assert_eq!(code, u32::from(expected)),
+        error => panic!("expected a program error, got {error:?}"),
+    }
+}
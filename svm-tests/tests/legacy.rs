@@ -0,0 +1,61 @@
+// Red packets left by the first program version, written into the bank with its account layout
+// and closed with withdraw_legacy_red_packet.
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+use redpacket_client::{
+    red_packet_address,
+    redpacket::{legacy, legacy::LegacyRedPacket, CustomError},
+    withdraw_legacy_red_packet, Asset, RedPacket, PROGRAM_ID,
+};
+use redpacket_svm_tests::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{account::Account, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+// A native legacy packet of 2 SOL with 1 of its 2 shares claimed, expiring in a day
+async fn legacy_native(context: &mut ProgramTestContext, creator: &Keypair) -> (Pubkey, LegacyRedPacket) {
+    let create_time = now(context).await;
+    let red_packet = LegacyRedPacket {
+        creator: creator.pubkey(),
+        total_number: 2,
+        claimed_number: 1,
+        total_amount: 2 * LAMPORTS_PER_SOL,
+        claimed_amount: LAMPORTS_PER_SOL,
+        create_time,
+        duration: 60 * 60 * 24,
+        token_type: legacy::TOKEN_TYPE_NATIVE,
+        token_address: Default::default(),
+        if_spilt_random: false,
+        claimed_users: vec![Keypair::new().pubkey()],
+        claimed_amount_records: vec![LAMPORTS_PER_SOL],
+        pubkey_for_claim_signature: Keypair::new().pubkey(),
+        name: "legacy".into(),
+        message: "legacy red packet".into(),
+    };
+    let mut data = RedPacket::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&red_packet.try_to_vec().unwrap());
+    data.resize(LegacyRedPacket::ACCOUNT_SIZE, 0);
+    let lamports = rent_exempt_lamports(context, data.len()).await + LAMPORTS_PER_SOL;
+    let address = red_packet_address(&creator.pubkey(), create_time).0;
+    context.set_account(&address, &Account { lamports, data, owner: PROGRAM_ID, executable: false, rent_epoch: 0 }.into());
+    (address, red_packet)
+}
+
+#[tokio::test]
+async fn creator_withdraws_legacy_red_packet_before_expiry() {
+    let Some(mut context) = start().await else { return };
+    let creator = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let (address, red_packet) = legacy_native(&mut context, &creator).await;
+    let instruction = withdraw_legacy_red_packet(&address, &red_packet, &Asset::Native);
+
+    // only the creator signs for the refund
+    let other = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let mut not_creator = instruction.clone();
+    not_creator.accounts[0].pubkey = other.pubkey();
+    assert_custom_error(send(&mut context, &[not_creator], &[&other]).await, CustomError::Unauthorized);
+
+    let creator_balance = balance(&mut context, &creator.pubkey()).await;
+    let red_packet_lamports = balance(&mut context, &address).await;
+    send(&mut context, &[instruction], &[&creator]).await.unwrap();
+    assert_eq!(balance(&mut context, &creator.pubkey()).await, creator_balance + red_packet_lamports);
+    assert!(!account_exists(&mut context, &address).await);
+}
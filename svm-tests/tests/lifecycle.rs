@@ -0,0 +1,164 @@
+// create -> claim -> withdraw on the compiled program, warping the clock across the expiry
+// boundary. Run `anchor build` first so target/deploy/redpacket.so exists, otherwise each test
+// is skipped with a note on stderr.
+
+use anchor_spl::{associated_token::get_associated_token_address_with_program_id, token, token_2022};
+use redpacket_client::{
+    claim_red_packet_with_signature, create_red_packet, create_red_packet_with_id, red_packet_address,
+    red_packet_address_with_id, redpacket::CustomError, vault_address, withdraw_red_packet, Asset,
+};
+use redpacket_svm_tests::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+const DURATION: u64 = 60 * 60;
+
+async fn claim(
+    context: &mut ProgramTestContext,
+    claim_signer: &Keypair,
+    red_packet: &Pubkey,
+    asset: &Asset,
+    claimer: &Keypair,
+    recipient: &Pubkey,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let account = fetch_red_packet(context, red_packet).await;
+    let signature = sign_claim(claim_signer, red_packet, &claimer.pubkey(), recipient);
+    let instructions =
+        claim_red_packet_with_signature(red_packet, &account, asset, &claimer.pubkey(), recipient, &signature, false);
+    send(context, &instructions, &[claimer]).await
+}
+
+async fn withdraw(
+    context: &mut ProgramTestContext,
+    creator: &Keypair,
+    red_packet: &Pubkey,
+    asset: &Asset,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let account = fetch_red_packet(context, red_packet).await;
+    send(context, &[withdraw_red_packet(red_packet, &account, asset)], &[creator]).await
+}
+
+// A native red packet with create_time based seeds, 2 shares of 1 SOL
+async fn create_native(context: &mut ProgramTestContext, creator: &Keypair, claim_signer: &Keypair) -> Pubkey {
+    let create_time = now(context).await;
+    let params = create_params(&claim_signer.pubkey(), 2, 2 * LAMPORTS_PER_SOL, DURATION);
+    let instruction = create_red_packet(&creator.pubkey(), create_time, &Asset::Native, params);
+    send(context, &[instruction], &[creator]).await.unwrap();
+    red_packet_address(&creator.pubkey(), create_time).0
+}
+
+#[tokio::test]
+async fn native_create_claim_withdraw() {
+    let Some(mut context) = start().await else { return };
+    let creator = funded_keypair(&mut context, 10 * LAMPORTS_PER_SOL).await;
+    let claimer = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let recipient = Keypair::new().pubkey();
+    let claim_signer = Keypair::new();
+    let red_packet = create_native(&mut context, &creator, &claim_signer).await;
+    let expiry = fetch_red_packet(&mut context, &red_packet).await.create_time + DURATION;
+
+    claim(&mut context, &claim_signer, &red_packet, &Asset::Native, &claimer, &recipient).await.unwrap();
+    assert_eq!(balance(&mut context, &recipient).await, LAMPORTS_PER_SOL);
+
+    warp_to(&mut context, expiry - 1).await;
+    assert_custom_error(
+        withdraw(&mut context, &creator, &red_packet, &Asset::Native).await,
+        CustomError::RedPacketNotExpired,
+    );
+
+    warp_to(&mut context, expiry).await;
+    let creator_balance = balance(&mut context, &creator.pubkey()).await;
+    // the unclaimed SOL plus the account rent
+    let red_packet_lamports = balance(&mut context, &red_packet).await;
+    withdraw(&mut context, &creator, &red_packet, &Asset::Native).await.unwrap();
+    assert_eq!(balance(&mut context, &creator.pubkey()).await, creator_balance + red_packet_lamports);
+    assert!(!account_exists(&mut context, &red_packet).await);
+}
+
+#[tokio::test]
+async fn claims_stop_at_expiry() {
+    let Some(mut context) = start().await else { return };
+    let creator = funded_keypair(&mut context, 10 * LAMPORTS_PER_SOL).await;
+    let first = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let second = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let claim_signer = Keypair::new();
+    let red_packet = create_native(&mut context, &creator, &claim_signer).await;
+    let expiry = fetch_red_packet(&mut context, &red_packet).await.create_time + DURATION;
+
+    warp_to(&mut context, expiry - 1).await;
+    claim(&mut context, &claim_signer, &red_packet, &Asset::Native, &first, &first.pubkey()).await.unwrap();
+
+    warp_to(&mut context, expiry).await;
+    assert_custom_error(
+        claim(&mut context, &claim_signer, &red_packet, &Asset::Native, &second, &second.pubkey()).await,
+        CustomError::RedPacketExpired,
+    );
+    assert_eq!(fetch_red_packet(&mut context, &red_packet).await.claimed_number, 1);
+}
+
+#[tokio::test]
+async fn claims_wait_for_open_time() {
+    let Some(mut context) = start().await else { return };
+    let creator = funded_keypair(&mut context, 10 * LAMPORTS_PER_SOL).await;
+    let claimer = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let claim_signer = Keypair::new();
+    let create_time = now(&mut context).await;
+    let mut params = create_params(&claim_signer.pubkey(), 2, 2 * LAMPORTS_PER_SOL, DURATION);
+    params.open_time = create_time + 600;
+    let instruction = create_red_packet(&creator.pubkey(), create_time, &Asset::Native, params);
+    send(&mut context, &[instruction], &[&creator]).await.unwrap();
+    let red_packet = red_packet_address(&creator.pubkey(), create_time).0;
+
+    warp_to(&mut context, create_time + 599).await;
+    assert_custom_error(
+        claim(&mut context, &claim_signer, &red_packet, &Asset::Native, &claimer, &claimer.pubkey()).await,
+        CustomError::RedPacketNotOpen,
+    );
+
+    warp_to(&mut context, create_time + 600).await;
+    claim(&mut context, &claim_signer, &red_packet, &Asset::Native, &claimer, &claimer.pubkey()).await.unwrap();
+}
+
+// An id addressed red packet of 4 shares of 100 tokens out of the creator's 1000
+async fn spl_create_claim_withdraw(token_program: Pubkey) {
+    let Some(mut context) = start().await else { return };
+    let creator = funded_keypair(&mut context, 10 * LAMPORTS_PER_SOL).await;
+    let claimer = funded_keypair(&mut context, LAMPORTS_PER_SOL).await;
+    let recipient = Keypair::new().pubkey();
+    let claim_signer = Keypair::new();
+    let mint = create_mint(&mut context, &token_program).await;
+    let creator_token_account = mint_to(&mut context, &token_program, &mint, &creator.pubkey(), 1000).await;
+    let asset = Asset::SplToken { mint, token_program };
+
+    let id = Keypair::new().pubkey().to_bytes();
+    let params = create_params(&claim_signer.pubkey(), 4, 400, DURATION);
+    let instruction = create_red_packet_with_id(&creator.pubkey(), id, &asset, params);
+    send(&mut context, &[instruction], &[&creator]).await.unwrap();
+    let red_packet = red_packet_address_with_id(&creator.pubkey(), &id).0;
+    let vault = vault_address(&red_packet, &mint, &token_program);
+    assert_eq!(token_balance(&mut context, &vault).await, 400);
+    let expiry = fetch_red_packet(&mut context, &red_packet).await.create_time + DURATION;
+
+    claim(&mut context, &claim_signer, &red_packet, &asset, &claimer, &recipient).await.unwrap();
+    let recipient_token_account = get_associated_token_address_with_program_id(&recipient, &mint, &token_program);
+    assert_eq!(token_balance(&mut context, &recipient_token_account).await, 100);
+
+    warp_to(&mut context, expiry - 1).await;
+    assert_custom_error(withdraw(&mut context, &creator, &red_packet, &asset).await, CustomError::RedPacketNotExpired);
+
+    warp_to(&mut context, expiry).await;
+    withdraw(&mut context, &creator, &red_packet, &asset).await.unwrap();
+    assert_eq!(token_balance(&mut context, &creator_token_account).await, 900);
+    assert!(!account_exists(&mut context, &vault).await);
+    assert!(!account_exists(&mut context, &red_packet).await);
+}
+
+#[tokio::test]
+async fn spl_token_create_claim_withdraw() {
+    spl_create_claim_withdraw(token::ID).await;
+}
+
+#[tokio::test]
+async fn token_2022_create_claim_withdraw() {
+    spl_create_claim_withdraw(token_2022::ID).await;
+}
@@ -12,6 +12,7 @@ import {
   PublicKey,
   SYSVAR_INSTRUCTIONS_PUBKEY,
   Keypair,
+  Secp256k1Program,
 } from "@solana/web3.js";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -21,12 +22,14 @@ import {
   getAssociatedTokenAddressSync,
   getOrCreateAssociatedTokenAccount,
   mintTo,
+  transfer,
 } from "@solana/spl-token";
 import { assert, expect } from "chai";
 import "dotenv/config";
 
 import nacl from "tweetnacl";
 import bs58 from "bs58";
+import { createHash } from "crypto";
 
 // Work on both Token Program and new Token Extensions Program
 const TOKEN_PROGRAM: typeof TOKEN_2022_PROGRAM_ID | typeof TOKEN_PROGRAM_ID =
@@ -193,10 +196,14 @@ describe("redpacket", () => {
           redPacketTotalAmount,
           splRedPacketCreateTime,
           redPacketDuration,
-          false,
+          splRedPacketCreateTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "my first spl red packet",
-          "my first spl red packet"
+          "my first spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -261,8 +268,8 @@ describe("redpacket", () => {
     expect(redPacketAccount.duration.toString()).equal(
       redPacketAccount.duration.toString()
     );
-    expect(redPacketAccount.tokenType).equal(1);
-    expect(redPacketAccount.ifSpiltRandom).equal(false);
+    expect(redPacketAccount.tokenType).to.deep.equal({ splToken: {} });
+    expect(redPacketAccount.splitMode).to.deep.equal({ equal: {} });
     expect(redPacketAccount.claimedNumber.toString()).equal("0");
     expect(redPacketAccount.claimedAmount.toString()).equal("0");
     expect(redPacketAccount.creator.toString()).equal(
@@ -299,10 +306,14 @@ describe("redpacket", () => {
         redPacketTotalAmount,
         nativeRedPacketCreateTime,
         redPacketDuration,
-        false, // if_split_random
+        nativeRedPacketCreateTime, // open_time
+        { equal: {} }, // split_mode
         claimer_issuer.publicKey,
         "my first native red packet",
-        "my first native red packet"
+        "my first native red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
       )
       .accounts({
         signer: redPacketCreator.publicKey,
@@ -330,8 +341,8 @@ describe("redpacket", () => {
       redPacketTotalAmount.toString()
     );
 
-    expect(redPacketAccount.tokenType).equal(0);
-    expect(redPacketAccount.ifSpiltRandom).equal(false);
+    expect(redPacketAccount.tokenType).to.deep.equal({ native: {} });
+    expect(redPacketAccount.splitMode).to.deep.equal({ equal: {} });
     expect(redPacketAccount.claimedNumber.toString()).equal("0");
     expect(redPacketAccount.claimedAmount.toString()).equal("0");
     expect(redPacketAccount.creator.toString()).equal(
@@ -376,6 +387,7 @@ describe("redpacket", () => {
       const message = Buffer.concat([
         redPacket.toBytes(),
         randomUser.publicKey.toBytes(),
+        randomUser.publicKey.toBytes(),
       ]);
 
       // Sign the message
@@ -396,12 +408,16 @@ describe("redpacket", () => {
       console.log("Signature verification in TS:", verifyResult);
 
       const tx = await redPacketProgram.methods
-        .claimWithSplToken()
+        .claimWithSplToken({ none: {} })
         .accounts({
           signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
           redPacket,
           tokenMint: tokenMint,
-          tokenAccount: claimerTokenAccount,
+          recipientTokenAccount: claimerTokenAccount,
           vault: vaultAccount,
           tokenProgram: TOKEN_PROGRAM,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -474,6 +490,7 @@ describe("redpacket", () => {
       const message = Buffer.concat([
         redPacket.toBytes(),
         randomUser2.publicKey.toBytes(),
+        randomUser2.publicKey.toBytes(),
       ]);
       // randomUser sign the message, so the signature is invalid
       const signature = nacl.sign.detached(message, randomUser.secretKey);
@@ -495,12 +512,16 @@ describe("redpacket", () => {
       console.log("Signature verification in TS:", verifyResult);
 
       const tx = await redPacketProgram.methods
-        .claimWithSplToken()
+        .claimWithSplToken({ none: {} })
         .accounts({
           signer: randomUser2.publicKey,
+          recipient: randomUser2.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
           redPacket,
           tokenMint: tokenMint,
-          tokenAccount: claimerTokenAccount,
+          recipientTokenAccount: claimerTokenAccount,
           vault: vaultAccount,
           tokenProgram: TOKEN_PROGRAM,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -520,6 +541,92 @@ describe("redpacket", () => {
     }
   });
 
+  it("fail to claim spl red packet from a vault of another mint", async () => {
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(splRedPacketCreateTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+
+    // tokens of another mint sent to the red packet's associated token account
+    const otherMint = await createMint(
+      connection,
+      signer,
+      signer.publicKey,
+      null,
+      9,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const otherVault = await getOrCreateAssociatedTokenAccount(
+      connection,
+      signer,
+      otherMint,
+      redPacket,
+      true,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    await mintTo(
+      connection,
+      signer,
+      otherMint,
+      otherVault.address,
+      signer,
+      1000,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser2.publicKey.toBytes(),
+      randomUser2.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    try {
+      await redPacketProgram.methods
+        .claimWithSplToken({ none: {} })
+        .accounts({
+          signer: randomUser2.publicKey,
+          recipient: randomUser2.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          redPacket,
+          tokenMint: otherMint,
+          recipientTokenAccount: getAssociatedTokenAddressSync(
+            otherMint,
+            randomUser2.publicKey,
+            true,
+            TOKEN_PROGRAM
+          ),
+          vault: otherVault.address,
+          tokenProgram: TOKEN_PROGRAM,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([randomUser2])
+        .rpc();
+      assert.fail("Expected transaction to fail with InvalidTokenType error");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidTokenType");
+    }
+  });
+
   it("claim native token red packet", async () => {
     // Re-derive the PDA
     const redPacket = PublicKey.findProgramAddressSync(
@@ -537,6 +644,7 @@ describe("redpacket", () => {
     const message = Buffer.concat([
       redPacket.toBytes(),
       randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
     ]);
     console.log("Original message:", bs58.encode(message));
 
@@ -550,10 +658,14 @@ describe("redpacket", () => {
     });
 
     const tx = await redPacketProgram.methods
-      .claimWithNativeToken()
+      .claimWithNativeToken({ none: {} })
       .accounts({
         redPacket,
         signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .preInstructions([ed25519Instruction])
@@ -579,9 +691,72 @@ describe("redpacket", () => {
     expect(redPacketAccount.claimedUsers[0].toString()).equal(
       randomUser.publicKey.toString()
     );
+    expect(redPacketAccount.claimedRecipients[0].toString()).equal(
+      randomUser.publicKey.toString()
+    );
     expect(redPacketAccount.claimedAmountRecords.length).to.equal(1);
   });
 
+  it("claim native token red packet to a separate recipient", async () => {
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(nativeRedPacketCreateTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const recipient = Keypair.generate();
+
+    // The claim signature covers the recipient, so the backend decides where funds go
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser2.publicKey.toBytes(),
+      recipient.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const ed25519Instruction = Ed25519Program.createInstructionWithPublicKey({
+      publicKey: claimer_issuer.publicKey.toBytes(),
+      message: message,
+      signature: signature,
+    });
+
+    const claimerBalanceBefore = await connection.getBalance(
+      randomUser2.publicKey
+    );
+    const tx = await redPacketProgram.methods
+      .claimWithNativeToken({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser2.publicKey,
+        recipient: recipient.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([ed25519Instruction])
+      .signers([randomUser2])
+      .rpc();
+    await provider.connection.confirmTransaction(tx);
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber.toString()).equal("2");
+    expect(redPacketAccount.claimedUsers[1].toString()).equal(
+      randomUser2.publicKey.toString()
+    );
+    expect(redPacketAccount.claimedRecipients[1].toString()).equal(
+      recipient.publicKey.toString()
+    );
+    expect(await connection.getBalance(recipient.publicKey)).equal(
+      1 * LAMPORTS_PER_SOL
+    );
+    expect(
+      await connection.getBalance(randomUser2.publicKey)
+    ).to.be.lessThanOrEqual(claimerBalanceBefore);
+  });
+
   it("create and claim spl token red packet with random amount", async () => {
     const splRandomRedPacketCreateTime = new anchor.BN(
       Math.floor(Date.now() / 1000) + 5
@@ -610,10 +785,14 @@ describe("redpacket", () => {
         redPacketTotalAmount,
         splRandomRedPacketCreateTime,
         redPacketDuration,
-        true, // if_split_random
+        splRandomRedPacketCreateTime, // open_time
+        { random: { split: { legacy: {} } } }, // split_mode
         claimer_issuer.publicKey,
         "my first random amount spl red packet",
-        "my first random amount spl red packet"
+        "my first random amount spl red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
       )
       .accounts({
         signer: redPacketCreator.publicKey,
@@ -638,12 +817,13 @@ describe("redpacket", () => {
     expect(redPacketAccount.totalAmount.toString()).equal(
       redPacketTotalAmount.toString()
     );
-    expect(redPacketAccount.ifSpiltRandom).equal(true);
+    expect(redPacketAccount.splitMode).to.have.property("random");
 
     // Generate the message
     const message = Buffer.concat([
       redPacket.toBytes(),
       randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -662,12 +842,16 @@ describe("redpacket", () => {
       TOKEN_PROGRAM
     );
     const claimTx = await redPacketProgram.methods
-      .claimWithSplToken()
+      .claimWithSplToken({ none: {} })
       .accounts({
         redPacket,
         signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         tokenMint: tokenMint,
-        tokenAccount: claimer1TokenAccount,
+        recipientTokenAccount: claimer1TokenAccount,
         vault: vault,
         tokenProgram: TOKEN_PROGRAM,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -682,6 +866,7 @@ describe("redpacket", () => {
     const message2 = Buffer.concat([
       redPacket.toBytes(),
       randomUser2.publicKey.toBytes(),
+      randomUser2.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -699,12 +884,16 @@ describe("redpacket", () => {
       TOKEN_PROGRAM
     );
     const claimTx2 = await redPacketProgram.methods
-      .claimWithSplToken()
+      .claimWithSplToken({ none: {} })
       .accounts({
         redPacket,
         signer: randomUser2.publicKey,
+        recipient: randomUser2.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         tokenMint: tokenMint,
-        tokenAccount: claimer2TokenAccount,
+        recipientTokenAccount: claimer2TokenAccount,
         vault: vault,
         tokenProgram: TOKEN_PROGRAM,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -718,6 +907,7 @@ describe("redpacket", () => {
     const message3 = Buffer.concat([
       redPacket.toBytes(),
       redPacketCreator.publicKey.toBytes(),
+      redPacketCreator.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -730,12 +920,16 @@ describe("redpacket", () => {
     });
 
     const claimTx3 = await redPacketProgram.methods
-      .claimWithSplToken()
+      .claimWithSplToken({ none: {} })
       .accounts({
         redPacket,
         signer: redPacketCreator.publicKey,
+        recipient: redPacketCreator.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         tokenMint: tokenMint,
-        tokenAccount: tokenAccount,
+        recipientTokenAccount: tokenAccount,
         vault: vault,
         tokenProgram: TOKEN_PROGRAM,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -788,10 +982,14 @@ describe("redpacket", () => {
         redPacketTotalAmount,
         nativeRandomRedPacketCreateTime,
         redPacketDuration,
-        true, // if_split_random
+        nativeRandomRedPacketCreateTime, // open_time
+        { random: { split: { legacy: {} } } }, // split_mode
         claimer_issuer.publicKey,
         "中文红包",
-        "中文红包测试"
+        "中文红包测试",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
       )
       .accounts({
         signer: redPacketCreator.publicKey,
@@ -811,12 +1009,13 @@ describe("redpacket", () => {
     expect(redPacketAccount.totalAmount.toString()).equal(
       redPacketTotalAmount.toString()
     );
-    expect(redPacketAccount.ifSpiltRandom).equal(true);
+    expect(redPacketAccount.splitMode).to.have.property("random");
 
     // Generate the message
     const message = Buffer.concat([
       redPacket.toBytes(),
       randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -828,10 +1027,14 @@ describe("redpacket", () => {
       signature: signature,
     });
     const claimTx = await redPacketProgram.methods
-      .claimWithNativeToken()
+      .claimWithNativeToken({ none: {} })
       .accounts({
         redPacket,
         signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .preInstructions([ed25519Instruction])
@@ -843,6 +1046,7 @@ describe("redpacket", () => {
     const message2 = Buffer.concat([
       redPacket.toBytes(),
       randomUser2.publicKey.toBytes(),
+      randomUser2.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -854,10 +1058,14 @@ describe("redpacket", () => {
       signature: signature2,
     });
     const claimTx2 = await redPacketProgram.methods
-      .claimWithNativeToken()
+      .claimWithNativeToken({ none: {} })
       .accounts({
         redPacket,
         signer: randomUser2.publicKey,
+        recipient: randomUser2.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .preInstructions([ed25519Instruction2])
@@ -868,6 +1076,7 @@ describe("redpacket", () => {
     const message3 = Buffer.concat([
       redPacket.toBytes(),
       redPacketCreator.publicKey.toBytes(),
+      redPacketCreator.publicKey.toBytes(),
     ]);
 
     // Sign the message
@@ -880,10 +1089,14 @@ describe("redpacket", () => {
     });
 
     const claimTx3 = await redPacketProgram.methods
-      .claimWithNativeToken()
+      .claimWithNativeToken({ none: {} })
       .accounts({
         redPacket,
         signer: redPacketCreator.publicKey,
+        recipient: redPacketCreator.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .preInstructions([ed25519Instruction3])
@@ -912,77 +1125,287 @@ describe("redpacket", () => {
     );
   });
 
-  it("withdraw spl token red packet", async () => {
-    // Re-derive the PDA
+  it("create and claim native token red packet with claim receipts", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 9);
     const redPacket = PublicKey.findProgramAddressSync(
       [
         redPacketCreator.publicKey.toBuffer(),
-        Buffer.from(splRedPacketCreateTime.toArray("le", 8)),
+        Buffer.from(createTime.toArray("le", 8)),
       ],
       redPacketProgram.programId
     )[0];
+    // more shares than fit in the in-account claim records
+    const redPacketTotalNumber = 10000;
+    const redPacketTotalAmount = new anchor.BN(1 * LAMPORTS_PER_SOL);
 
-    // Now perform withdrawal
-    const withdrawTx = await redPacketProgram.methods
-      .withdrawWithSplToken()
-      .accounts({
-        redPacket,
-        signer: redPacketCreator.publicKey,
-        vault: vault,
-        tokenAccount: tokenAccount, // Will be created if it doesn't exist
-        tokenMint: tokenMint,
-        tokenProgram: TOKEN_PROGRAM,
-        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-      })
-      .signers([redPacketCreator])
-      .rpc();
+    const create = () =>
+      redPacketProgram.methods
+        .createRedPacketWithNativeToken(
+          redPacketTotalNumber,
+          redPacketTotalAmount,
+          createTime,
+          new anchor.BN(60 * 60 * 24),
+          createTime, // open_time
+          { equal: {} }, // split_mode
+          claimer_issuer.publicKey,
+          "receipt red packet",
+          "receipt red packet",
+          true, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+    await provider.connection.confirmTransaction(await create());
 
-    await provider.connection.confirmTransaction(withdrawTx);
+    const claimReceipt = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("claim_receipt"),
+        redPacket.toBuffer(),
+        randomUser.publicKey.toBuffer(),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const claim = (recipient: PublicKey) => {
+      const message = Buffer.concat([
+        redPacket.toBytes(),
+        randomUser.publicKey.toBytes(),
+        recipient.toBytes(),
+      ]);
+      const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+      const ed25519Instruction = Ed25519Program.createInstructionWithPublicKey(
+        {
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }
+      );
+      return redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient,
+          claimReceipt,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([ed25519Instruction])
+        .signers([randomUser])
+        .rpc();
+    };
+
+    await provider.connection.confirmTransaction(
+      await claim(randomUser.publicKey)
+    );
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.useClaimReceipts).equal(true);
+    expect(redPacketAccount.claimedNumber.toString()).equal("1");
+    expect(redPacketAccount.claimedUsers.length).equal(0);
+
+    const receipt = await redPacketProgram.account.claimReceipt.fetch(
+      claimReceipt
+    );
+    expect(receipt.claimer.toString()).equal(randomUser.publicKey.toString());
+    expect(receipt.recipient.toString()).equal(
+      randomUser.publicKey.toString()
+    );
+    expect(receipt.amount.toString()).equal(
+      redPacketTotalAmount.divn(redPacketTotalNumber).toString()
+    );
 
-    // Verify the account is closed
     try {
-      await redPacketProgram.account.redPacket.fetch(redPacket);
-      assert.fail("Expected account to be closed");
+      // a second claim by the same claimer hits the existing receipt
+      await claim(randomUser2.publicKey);
+      assert.fail("Expected transaction to fail with RedPacketClaimed error");
     } catch (error) {
-      expect(error.message).to.include("Account does not exist");
+      expect(error.error.errorCode.code).to.equal("RedPacketClaimed");
+    }
+
+    // the receipt rent goes back to the claimer once the red packet is closed
+    const closeReceipt = () =>
+      redPacketProgram.methods
+        .closeClaimReceipt()
+        .accounts({ signer: randomUser.publicKey, redPacket, claimReceipt })
+        .signers([randomUser])
+        .rpc();
+    try {
+      await closeReceipt();
+      assert.fail("Expected transaction to fail with RedPacketNotClosed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RedPacketNotClosed");
     }
+    const creatorAccounts = {
+      signer: redPacketCreator.publicKey,
+      redPacket,
+      tokenMint: null,
+      tokenAccount: null,
+      vault: null,
+      tokenProgram: null,
+      associatedTokenProgram: null,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+    const cancelAndWithdraw = async () => {
+      await provider.connection.confirmTransaction(
+        await redPacketProgram.methods
+          .cancelRedPacket()
+          .accounts(creatorAccounts)
+          .signers([redPacketCreator])
+          .rpc()
+      );
+      await provider.connection.confirmTransaction(
+        await redPacketProgram.methods
+          .withdrawRedPacket()
+          .accounts(creatorAccounts)
+          .signers([redPacketCreator])
+          .rpc()
+      );
+    };
+    await cancelAndWithdraw();
+
+    // a red packet created again at the same address does not count the old receipt as a claim,
+    // the new claim overwrites it
+    await provider.connection.confirmTransaction(await create());
+    await provider.connection.confirmTransaction(
+      await claim(randomUser.publicKey)
+    );
+    const recreated = await redPacketProgram.account.redPacket.fetch(redPacket);
+    const newReceipt = await redPacketProgram.account.claimReceipt.fetch(
+      claimReceipt
+    );
+    expect(newReceipt.redPacketCreateSlot.toString()).equal(
+      recreated.createSlot.toString()
+    );
+    expect(newReceipt.redPacketCreateSlot.gt(receipt.redPacketCreateSlot)).to.be
+      .true;
+    await cancelAndWithdraw();
+
+    const receiptRent = await provider.connection.getBalance(claimReceipt);
+    const balanceBefore = await provider.connection.getBalance(
+      randomUser.publicKey
+    );
+    await provider.connection.confirmTransaction(await closeReceipt());
+    expect(await provider.connection.getAccountInfo(claimReceipt)).equal(null);
+    // the claimer also pays the transaction fee
+    expect(
+      (await provider.connection.getBalance(randomUser.publicKey)) -
+        balanceBefore
+    ).greaterThan(receiptRent - 10000);
   });
 
-  it("withdraw native token red packet", async () => {
-    // Re-derive the PDA
+  it("create and claim native token red packet with merkle allowlist", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 11);
     const redPacket = PublicKey.findProgramAddressSync(
       [
         redPacketCreator.publicKey.toBuffer(),
-        Buffer.from(nativeRedPacketCreateTime.toArray("le", 8)),
+        Buffer.from(createTime.toArray("le", 8)),
       ],
       redPacketProgram.programId
     )[0];
-    // Now perform withdrawal
-    const withdrawTx = await redPacketProgram.methods
-      .withdrawWithNativeToken()
+    const fixedAmount = new anchor.BN(0.4 * LAMPORTS_PER_SOL);
+    const leaves = [
+      allowlistLeaf(randomUser.publicKey, fixedAmount),
+      allowlistLeaf(randomUser2.publicKey, new anchor.BN(0)),
+      allowlistLeaf(redPacketCreator.publicKey, new anchor.BN(0)),
+    ];
+    const root = merkleRoot(leaves);
+
+    const tx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        3,
+        new anchor.BN(1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        PublicKey.default,
+        "allowlist red packet",
+        "allowlist red packet",
+        false, // use_claim_receipts
+        { merkleAllowlist: { root: Array.from(root) } },
+        { timestamp: {} } // randomness
+      )
       .accounts({
-        redPacket,
         signer: redPacketCreator.publicKey,
+        redPacket,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([redPacketCreator])
       .rpc();
+    await provider.connection.confirmTransaction(tx);
 
-    await provider.connection.confirmTransaction(withdrawTx);
-    await getLogs(withdrawTx);
-
-    // Verify the account is closed
+    // randomUser2 is allowlisted, but not with randomUser's fixed amount
     try {
-      await redPacketProgram.account.redPacket.fetch(redPacket);
-      assert.fail("Expected account to be closed");
+      await redPacketProgram.methods
+        .claimWithNativeToken({
+          merkle: {
+            proof: merkleProof(leaves, 1).map((node) => Array.from(node)),
+            amount: fixedAmount,
+          },
+        })
+        .accounts({
+          redPacket,
+          signer: randomUser2.publicKey,
+          recipient: randomUser2.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([randomUser2])
+        .rpc();
+      assert.fail("Expected transaction to fail with InvalidMerkleProof error");
     } catch (error) {
-      expect(error.message).to.include("Account does not exist");
+      expect(error.error.errorCode.code).to.equal("InvalidMerkleProof");
     }
+
+    // no Ed25519 pre-instruction is needed for allowlist claims
+    const claimerBalanceBefore = await connection.getBalance(
+      randomUser.publicKey
+    );
+    const claimTx = await redPacketProgram.methods
+      .claimWithNativeToken({
+        merkle: {
+          proof: merkleProof(leaves, 0).map((node) => Array.from(node)),
+          amount: fixedAmount,
+        },
+      })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(claimTx);
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedAmountRecords[0].toString()).equal(
+      fixedAmount.toString()
+    );
+    const claimerBalanceAfter = await connection.getBalance(
+      randomUser.publicKey
+    );
+    expect(claimerBalanceAfter).to.be.greaterThan(claimerBalanceBefore);
   });
 
-  it("fail to create spl red packet with invalid parameters", async () => {
-    // Re-derive the PDA
-    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) - 100);
+  it("create and claim native token red packet with password", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 13);
     const redPacket = PublicKey.findProgramAddressSync(
       [
         redPacketCreator.publicKey.toBuffer(),
@@ -990,17 +1413,1872 @@ describe("redpacket", () => {
       ],
       redPacketProgram.programId
     )[0];
+    const phrase = "恭喜发财";
+    const passwordHash = createHash("sha256")
+      .update(redPacket.toBuffer())
+      .update(Buffer.from(phrase))
+      .digest();
 
-    // Get vault account
-    const vaultAccount = getAssociatedTokenAddressSync(
-      tokenMint,
-      redPacket,
-      true,
-      TOKEN_PROGRAM
-    );
-    const duration = new anchor.BN(200);
-    const totalNumber = 1;
-    const totalAmount = new anchor.BN(1 * LAMPORTS_PER_SOL);
+    const tx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        2,
+        new anchor.BN(1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        PublicKey.default,
+        "password red packet",
+        "password red packet",
+        false, // use_claim_receipts
+        { password: { hash: Array.from(passwordHash) } },
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(tx);
+
+    const passwordCommit = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("password_commit"),
+        redPacket.toBuffer(),
+        randomUser.publicKey.toBuffer(),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const claim = (password: string) =>
+      redPacketProgram.methods
+        .claimWithNativeToken({ password: { phrase: password } })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          claimReceipt: null,
+          passwordCommit,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([randomUser])
+        .rpc();
+
+    // step 1: commit to the phrase, bound to the claimer key
+    const commitment = createHash("sha256")
+      .update(redPacket.toBuffer())
+      .update(randomUser.publicKey.toBuffer())
+      .update(Buffer.from(phrase))
+      .digest();
+    const commitTx = await redPacketProgram.methods
+      .commitPasswordClaim(Array.from(commitment))
+      .accounts({
+        signer: randomUser.publicKey,
+        redPacket,
+        passwordCommit,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(commitTx, "confirmed");
+
+    try {
+      await claim("恭喜");
+      assert.fail("Expected transaction to fail with InvalidPassword error");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidPassword");
+    }
+
+    // step 2: reveal the phrase in a later slot
+    const claimTx = await claim(phrase);
+    await provider.connection.confirmTransaction(claimTx);
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber.toString()).equal("1");
+    expect(await connection.getAccountInfo(passwordCommit)).to.be.null;
+  });
+
+  it("create and claim native token red packet with slot hashes randomness", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 15);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const seed = Keypair.generate().secretKey.slice(0, 32);
+    const seedCommitment = createHash("sha256").update(seed).digest();
+
+    const tx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        2,
+        new anchor.BN(1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { random: { split: { legacy: {} } } }, // split_mode
+        claimer_issuer.publicKey,
+        "slot hashes red packet",
+        "slot hashes red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { slotHashes: { seedCommitment: Array.from(seedCommitment) } }
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(tx);
+
+    const randomCommitOf = (claimer: Keypair) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("random_commit"),
+          redPacket.toBuffer(),
+          claimer.publicKey.toBuffer(),
+        ],
+        redPacketProgram.programId
+      )[0];
+    const commit = (claimer: Keypair) =>
+      redPacketProgram.methods
+        .commitRandomClaim()
+        .accounts({
+          signer: claimer.publicKey,
+          redPacket,
+          randomCommit: randomCommitOf(claimer),
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([claimer])
+        .rpc();
+    const claim = (claimer: Keypair, randomCommit: PublicKey | null) => {
+      const message = Buffer.concat([
+        redPacket.toBytes(),
+        claimer.publicKey.toBytes(),
+        claimer.publicKey.toBytes(),
+      ]);
+      const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+      return redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: claimer.publicKey,
+          recipient: claimer.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([claimer])
+        .rpc();
+    };
+
+    try {
+      await claim(randomUser, null);
+      assert.fail("Expected transaction to fail with RandomClaimNotCommitted");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RandomClaimNotCommitted");
+    }
+
+    // draws wait for the seed until the reveal deadline
+    try {
+      await commit(randomUser);
+      assert.fail("Expected transaction to fail with RandomSeedNotRevealed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RandomSeedNotRevealed");
+    }
+
+    try {
+      await redPacketProgram.methods
+        .revealRandomSeed(Array.from(seedCommitment))
+        .accounts({ signer: redPacketCreator.publicKey, redPacket })
+        .signers([redPacketCreator])
+        .rpc();
+      assert.fail("Expected transaction to fail with InvalidRandomSeed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidRandomSeed");
+    }
+
+    const revealTx = await redPacketProgram.methods
+      .revealRandomSeed(Array.from(seed))
+      .accounts({ signer: redPacketCreator.publicKey, redPacket })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(revealTx);
+
+    // a claim has to follow its commit within RANDOM_CLAIM_WINDOW_SLOTS (2), and the commit can
+    // only be closed and made again once its slot hash has left SlotHashes (512 slots)
+    const lateClaimer = Keypair.generate();
+    await confirmTransaction(
+      connection,
+      await connection.requestAirdrop(lateClaimer.publicKey, LAMPORTS_PER_SOL)
+    );
+    await provider.connection.confirmTransaction(
+      await commit(lateClaimer),
+      "confirmed"
+    );
+    const { slot: commitSlot } =
+      await redPacketProgram.account.randomCommit.fetch(
+        randomCommitOf(lateClaimer)
+      );
+    while ((await connection.getSlot()) <= commitSlot.toNumber() + 2) {
+      await new Promise((resolve) => setTimeout(resolve, 200));
+    }
+    try {
+      await claim(lateClaimer, randomCommitOf(lateClaimer));
+      assert.fail("Expected transaction to fail with RandomCommitExpired");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RandomCommitExpired");
+    }
+    try {
+      await redPacketProgram.methods
+        .closeRandomCommit()
+        .accounts({
+          signer: lateClaimer.publicKey,
+          redPacket,
+          randomCommit: randomCommitOf(lateClaimer),
+        })
+        .signers([lateClaimer])
+        .rpc();
+      assert.fail("Expected transaction to fail with RandomCommitNotExpired");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RandomCommitNotExpired");
+    }
+
+    // step 1: commit, step 2: claim from the commit slot's hash in one of the next slots
+    for (const claimer of [randomUser, randomUser2]) {
+      await provider.connection.confirmTransaction(
+        await commit(claimer),
+        "confirmed"
+      );
+      await provider.connection.confirmTransaction(
+        await claim(claimer, randomCommitOf(claimer))
+      );
+      expect(await connection.getAccountInfo(randomCommitOf(claimer))).to.be
+        .null;
+    }
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedAmount.toString()).equal(
+      (1 * LAMPORTS_PER_SOL).toString()
+    );
+  });
+
+  it("create and claim native token red packet with double-average split", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 17);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const totalAmount = new anchor.BN(0.9 * LAMPORTS_PER_SOL);
+    const minShare = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+    const maxShare = new anchor.BN(0.4 * LAMPORTS_PER_SOL);
+    const create = (min: anchor.BN) =>
+      redPacketProgram.methods
+        .createRedPacketWithNativeToken(
+          3,
+          totalAmount,
+          createTime,
+          new anchor.BN(60 * 60 * 24),
+          createTime, // open_time
+          { random: { split: { doubleAverage: { minShare: min, maxShare } } } }, // split_mode
+          claimer_issuer.publicKey,
+          "double average red packet",
+          "double average red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    try {
+      // 3 * 0.4 SOL minimum does not fit into 0.9 SOL
+      await create(maxShare);
+      assert.fail("Expected transaction to fail with InvalidSplitConfig");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidSplitConfig");
+    }
+    await provider.connection.confirmTransaction(await create(minShare));
+
+    for (const claimer of [randomUser, randomUser2, redPacketCreator]) {
+      const message = Buffer.concat([
+        redPacket.toBytes(),
+        claimer.publicKey.toBytes(),
+        claimer.publicKey.toBytes(),
+      ]);
+      const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+      const claimTx = await redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: claimer.publicKey,
+          recipient: claimer.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([claimer])
+        .rpc();
+      await provider.connection.confirmTransaction(claimTx);
+    }
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedAmount.toString()).equal(
+      totalAmount.toString()
+    );
+    for (const amount of redPacketAccount.claimedAmountRecords) {
+      expect(amount.gte(minShare)).to.be.true;
+      expect(amount.lte(maxShare)).to.be.true;
+    }
+  });
+
+  it("create and claim native token red packet with a fixed share list", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 19);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const firstPrize = new anchor.BN(0.5 * LAMPORTS_PER_SOL);
+    const secondPrize = new anchor.BN(0.25 * LAMPORTS_PER_SOL);
+    const create = (secondPrizeCount: number) =>
+      redPacketProgram.methods
+        .createRedPacketWithNativeToken(
+          3,
+          new anchor.BN(1 * LAMPORTS_PER_SOL),
+          createTime,
+          new anchor.BN(60 * 60 * 24),
+          createTime, // open_time
+          {
+            fixedList: {
+              tiers: [
+                { count: 1, amount: firstPrize },
+                { count: secondPrizeCount, amount: secondPrize },
+              ],
+              shuffled: false,
+            },
+          }, // split_mode
+          claimer_issuer.publicKey,
+          "fixed list red packet",
+          "fixed list red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    try {
+      // 0.5 + 1 * 0.25 SOL does not add up to 1 SOL for 3 shares
+      await create(1);
+      assert.fail("Expected transaction to fail with InvalidShareTiers");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidShareTiers");
+    }
+    await provider.connection.confirmTransaction(await create(2));
+
+    for (const claimer of [randomUser, randomUser2, redPacketCreator]) {
+      const message = Buffer.concat([
+        redPacket.toBytes(),
+        claimer.publicKey.toBytes(),
+        claimer.publicKey.toBytes(),
+      ]);
+      const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+      const claimTx = await redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: claimer.publicKey,
+          recipient: claimer.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([claimer])
+        .rpc();
+      await provider.connection.confirmTransaction(claimTx);
+    }
+
+    // handed out in claim order
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(
+      redPacketAccount.claimedAmountRecords.map((amount) => amount.toString())
+    ).to.deep.equal([
+      firstPrize.toString(),
+      secondPrize.toString(),
+      secondPrize.toString(),
+    ]);
+  });
+
+  it("create and claim native token red packet with the unified instructions", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 21);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const totalAmount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+    const create = (tokenMintAccount: PublicKey | null) =>
+      redPacketProgram.methods
+        .createRedPacket(
+          { native: {} }, // asset_kind
+          2,
+          totalAmount,
+          createTime,
+          new anchor.BN(60 * 60 * 24),
+          createTime, // open_time
+          { equal: {} }, // split_mode
+          claimer_issuer.publicKey,
+          "unified red packet",
+          "unified red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          tokenMint: tokenMintAccount,
+          tokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    try {
+      await create(tokenMint);
+      assert.fail("Expected transaction to fail with InvalidAccountForNativeToken");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidAccountForNativeToken");
+    }
+    await provider.connection.confirmTransaction(await create(null));
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const claimTx = await redPacketProgram.methods
+      .claimRedPacket({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        creator: null,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        tokenMint: null,
+        recipientTokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }),
+      ])
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(claimTx);
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.tokenType).to.deep.equal({ native: {} });
+    expect(redPacketAccount.claimedNumber).equal(1);
+    expect(redPacketAccount.claimedAmount.toString()).equal(
+      totalAmount.divn(2).toString()
+    );
+  });
+
+  it("top up native token red packet", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 23);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const totalAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        1,
+        totalAmount,
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "top up red packet",
+        "top up red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const topUp = (creator: anchor.web3.Keypair) =>
+      redPacketProgram.methods
+        .topUpRedPacket(2, totalAmount)
+        .accounts({
+          signer: creator.publicKey,
+          redPacket,
+          tokenMint: null,
+          tokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+
+    try {
+      await topUp(randomUser);
+      assert.fail("Expected transaction to fail with Unauthorized");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    const balanceBefore = await provider.connection.getBalance(redPacket);
+    await provider.connection.confirmTransaction(await topUp(redPacketCreator));
+    const balanceAfter = await provider.connection.getBalance(redPacket);
+    // plus the rent of the two new claim records (72 bytes each)
+    const recordsRent =
+      (await provider.connection.getMinimumBalanceForRentExemption(2 * 72)) -
+      (await provider.connection.getMinimumBalanceForRentExemption(0));
+    expect(balanceAfter - balanceBefore).equal(
+      totalAmount.toNumber() + recordsRent
+    );
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.totalNumber).equal(3);
+    expect(redPacketAccount.totalAmount.toString()).equal(
+      totalAmount.muln(2).toString()
+    );
+  });
+
+  it("update native token red packet expiry", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 25);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        2,
+        new anchor.BN(0.1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "expiry red packet",
+        "expiry red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const updateExpiry = (duration: number) =>
+      redPacketProgram.methods
+        .updateRedPacketExpiry(createTime.addn(duration))
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    // nobody has claimed yet, so the expiry can be brought forward
+    await provider.connection.confirmTransaction(await updateExpiry(60 * 60));
+    let redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.duration.toNumber()).equal(60 * 60);
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const claimTx = await redPacketProgram.methods
+      .claimWithNativeToken({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }),
+      ])
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(claimTx);
+
+    try {
+      await updateExpiry(60 * 30);
+      assert.fail("Expected transaction to fail with InvalidExpiryTime");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidExpiryTime");
+    }
+    await provider.connection.confirmTransaction(
+      await updateExpiry(60 * 60 * 48)
+    );
+    redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.duration.toNumber()).equal(60 * 60 * 48);
+  });
+
+  it("cancel native token red packet before expiry", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 27);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        2,
+        new anchor.BN(0.1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "cancelled red packet",
+        "cancelled red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    // the rent, including the rent reserved for claim records, stays until withdraw_red_packet
+    const balanceBeforeCancel = await provider.connection.getBalance(redPacket);
+    const cancelTx = await redPacketProgram.methods
+      .cancelRedPacket()
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(cancelTx);
+    expect(await provider.connection.getBalance(redPacket)).equal(
+      balanceBeforeCancel - 0.1 * LAMPORTS_PER_SOL
+    );
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.cancelled).to.be.true;
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    try {
+      await redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([randomUser])
+        .rpc();
+      assert.fail("Expected transaction to fail with RedPacketCancelled");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RedPacketCancelled");
+    }
+
+    // the rent can be reclaimed without waiting for the expiry
+    const withdrawTx = await redPacketProgram.methods
+      .withdrawRedPacket()
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(withdrawTx);
+    try {
+      await redPacketProgram.account.redPacket.fetch(redPacket);
+      assert.fail("Expected account to be closed");
+    } catch (error) {
+      expect(error.message).to.include("Account does not exist");
+    }
+  });
+
+  it("cancel spl token red packet refunds the whole vault balance", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 35);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const vault = getAssociatedTokenAddressSync(
+      tokenMint,
+      redPacket,
+      true,
+      TOKEN_PROGRAM
+    );
+    const totalAmount = new anchor.BN(1 * LAMPORTS_PER_SOL);
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithSplToken(
+        2,
+        totalAmount,
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "cancelled spl red packet",
+        "cancelled spl red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: tokenMint,
+        tokenAccount: tokenAccount,
+        vault: vault,
+        tokenProgram: TOKEN_PROGRAM,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    // tokens sent straight to the vault are refunded too
+    await transfer(
+      connection,
+      redPacketCreator,
+      tokenAccount,
+      vault,
+      redPacketCreator,
+      1000,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const cancelTx = await redPacketProgram.methods
+      .cancelRedPacket()
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: tokenMint,
+        tokenAccount: tokenAccount,
+        vault: vault,
+        tokenProgram: TOKEN_PROGRAM,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc({ commitment: "confirmed" });
+    const [cancelled] = await getCpiEvents(redPacketProgram, cancelTx);
+    expect(cancelled.name).equal("redPacketCancelled");
+    expect(cancelled.data.refundedAmount.toString()).equal(
+      totalAmount.addn(1000).toString()
+    );
+
+    const withdrawTx = await redPacketProgram.methods
+      .withdrawRedPacket()
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc({ commitment: "confirmed" });
+    const [withdrawn] = await getCpiEvents(redPacketProgram, withdrawTx);
+    expect(withdrawn.name).equal("redPacketWithdrawn");
+    expect(withdrawn.data.refundedAmount.toString()).equal("0");
+  });
+
+  it("last claim closes a fully claimed native token red packet", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 29);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacket(
+        { native: {} }, // asset_kind
+        1,
+        new anchor.BN(0.1 * LAMPORTS_PER_SOL),
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "auto close red packet",
+        "auto close red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const rent = await provider.connection.getBalance(redPacket) - 0.1 * LAMPORTS_PER_SOL;
+    const creatorBalanceBefore = await provider.connection.getBalance(
+      redPacketCreator.publicKey
+    );
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const claimTx = await redPacketProgram.methods
+      .claimRedPacket({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        creator: redPacketCreator.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        tokenMint: null,
+        recipientTokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }),
+      ])
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(claimTx);
+
+    expect(
+      await provider.connection.getBalance(redPacketCreator.publicKey)
+    ).equal(creatorBalanceBefore + rent);
+    try {
+      await redPacketProgram.account.redPacket.fetch(redPacket);
+      assert.fail("Expected account to be closed");
+    } catch (error) {
+      expect(error.message).to.include("Account does not exist");
+    }
+  });
+
+  it("native token red packet cannot be claimed before its open time", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 31);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const duration = new anchor.BN(60 * 60 * 24);
+    const create = (openTime: anchor.BN) =>
+      redPacketProgram.methods
+        .createRedPacketWithNativeToken(
+          1,
+          new anchor.BN(0.1 * LAMPORTS_PER_SOL),
+          createTime,
+          duration,
+          openTime, // open_time
+          { equal: {} }, // split_mode
+          claimer_issuer.publicKey,
+          "scheduled red packet",
+          "scheduled red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    try {
+      await create(createTime.add(duration));
+      assert.fail("Expected transaction to fail with InvalidOpenTime");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidOpenTime");
+    }
+    const openTime = createTime.addn(60 * 60);
+    await provider.connection.confirmTransaction(await create(openTime));
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.openTime.toString()).equal(openTime.toString());
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    try {
+      await redPacketProgram.methods
+        .claimWithNativeToken({ none: {} })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: signature,
+          }),
+        ])
+        .signers([randomUser])
+        .rpc();
+      assert.fail("Expected transaction to fail with RedPacketNotOpen");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("RedPacketNotOpen");
+    }
+  });
+
+  it("create and claim native token red packet addressed by id", async () => {
+    const id = anchor.web3.Keypair.generate().publicKey.toBytes();
+    const redPacket = PublicKey.findProgramAddressSync(
+      [redPacketCreator.publicKey.toBuffer(), Buffer.from(id)],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithId(
+        Array.from(id),
+        { native: {} }, // asset_kind
+        2,
+        new anchor.BN(0.2 * LAMPORTS_PER_SOL),
+        new anchor.BN(60 * 60 * 24),
+        new anchor.BN(0), // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "id red packet",
+        "id red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const claimTx = await redPacketProgram.methods
+      .claimRedPacket({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        creator: null,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        tokenMint: null,
+        recipientTokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }),
+      ])
+      .signers([randomUser])
+      .rpc();
+    await provider.connection.confirmTransaction(claimTx);
+
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.address).to.deep.equal({ id: { id: Array.from(id) } });
+    expect(redPacketAccount.claimedNumber).equal(1);
+  });
+
+  it("emit created and claimed events for native token red packet", async () => {
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) + 33);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    const totalAmount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithNativeToken(
+        2,
+        totalAmount,
+        createTime,
+        new anchor.BN(60 * 60 * 24),
+        createTime, // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "event red packet",
+        "event red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc({ commitment: "confirmed" });
+
+    const [created] = await getCpiEvents(redPacketProgram, createTx);
+    expect(created.name).equal("redPacketCreated");
+    expect(created.data.redPacket.toBase58()).equal(redPacket.toBase58());
+    expect(created.data.totalAmount.toString()).equal(totalAmount.toString());
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const signature = nacl.sign.detached(message, claimer_issuer.secretKey);
+    const claimTx = await redPacketProgram.methods
+      .claimWithNativeToken({ none: {} })
+      .accounts({
+        redPacket,
+        signer: randomUser.publicKey,
+        recipient: randomUser.publicKey,
+        claimReceipt: null,
+        passwordCommit: null,
+        randomCommit: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: signature,
+        }),
+      ])
+      .signers([randomUser])
+      .rpc({ commitment: "confirmed" });
+
+    const [claimed] = await getCpiEvents(redPacketProgram, claimTx);
+    expect(claimed.name).equal("redPacketClaimed");
+    expect(claimed.data.claimer.toBase58()).equal(
+      randomUser.publicKey.toBase58()
+    );
+    expect(claimed.data.amount.toString()).equal(
+      totalAmount.divn(2).toString()
+    );
+    expect(claimed.data.claimedNumber).equal(1);
+  });
+
+  it("claim native token red packet with an expiring v1 claim signature", async () => {
+    const id = anchor.web3.Keypair.generate().publicKey.toBytes();
+    const redPacket = PublicKey.findProgramAddressSync(
+      [redPacketCreator.publicKey.toBuffer(), Buffer.from(id)],
+      redPacketProgram.programId
+    )[0];
+    const totalAmount = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithId(
+        Array.from(id),
+        { native: {} }, // asset_kind
+        2,
+        totalAmount,
+        new anchor.BN(60 * 60 * 24),
+        new anchor.BN(0), // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "v1 red packet",
+        "v1 red packet",
+        false, // use_claim_receipts
+        { signatureV1: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    // domain || program id || red packet || claimer || recipient || deadline || max amount
+    const messageV1 = (deadline: anchor.BN, maxAmount: anchor.BN) =>
+      Buffer.concat([
+        Buffer.from([0xff]),
+        Buffer.from("redpacket claim v1"),
+        redPacketProgram.programId.toBuffer(),
+        redPacket.toBuffer(),
+        randomUser.publicKey.toBuffer(),
+        randomUser.publicKey.toBuffer(),
+        deadline.toArrayLike(Buffer, "le", 8),
+        maxAmount.toArrayLike(Buffer, "le", 8),
+      ]);
+    const claim = (message: Buffer, proof: any) =>
+      redPacketProgram.methods
+        .claimRedPacket(proof)
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          creator: null,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          tokenMint: null,
+          recipientTokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: claimer_issuer.publicKey.toBytes(),
+            message: message,
+            signature: nacl.sign.detached(message, claimer_issuer.secretKey),
+          }),
+        ])
+        .signers([randomUser])
+        .rpc();
+
+    const share = totalAmount.divn(2);
+    const expired = new anchor.BN(1);
+    try {
+      await claim(messageV1(expired, share), {
+        signatureV1: { deadline: expired, maxAmount: share },
+      });
+      assert.fail("Expected transaction to fail with ClaimSignatureExpired");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClaimSignatureExpired");
+    }
+
+    const deadline = new anchor.BN(Math.floor(Date.now() / 1000) + 600);
+    try {
+      await claim(messageV1(deadline, share.subn(1)), {
+        signatureV1: { deadline, maxAmount: share.subn(1) },
+      });
+      assert.fail("Expected transaction to fail with ClaimAmountAboveSignedMax");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClaimAmountAboveSignedMax");
+    }
+
+    // a legacy message is not accepted by a v1 red packet
+    const legacyMessage = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    try {
+      await claim(legacyMessage, { none: {} });
+      assert.fail("Expected transaction to fail with InvalidClaimProof");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidClaimProof");
+    }
+
+    await provider.connection.confirmTransaction(
+      await claim(messageV1(deadline, share), {
+        signatureV1: { deadline, maxAmount: share },
+      })
+    );
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber).equal(1);
+  });
+
+  it("rotate and pause the claim signer of a native token red packet", async () => {
+    const id = anchor.web3.Keypair.generate().publicKey.toBytes();
+    const redPacket = PublicKey.findProgramAddressSync(
+      [redPacketCreator.publicKey.toBuffer(), Buffer.from(id)],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithId(
+        Array.from(id),
+        { native: {} }, // asset_kind
+        2,
+        new anchor.BN(0.2 * LAMPORTS_PER_SOL),
+        new anchor.BN(60 * 60 * 24),
+        new anchor.BN(0), // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey,
+        "rotated red packet",
+        "rotated red packet",
+        false, // use_claim_receipts
+        { signature: {} }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const updateClaimSigner = (signer: Keypair, newSigner: PublicKey | null) =>
+      redPacketProgram.methods
+        .updateClaimSigner(newSigner)
+        .accounts({ signer: signer.publicKey, redPacket })
+        .signers([signer])
+        .rpc({ commitment: "confirmed" });
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const claim = (issuer: Keypair) =>
+      redPacketProgram.methods
+        .claimRedPacket({ none: {} })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          creator: null,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          tokenMint: null,
+          recipientTokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          Ed25519Program.createInstructionWithPublicKey({
+            publicKey: issuer.publicKey.toBytes(),
+            message: message,
+            signature: nacl.sign.detached(message, issuer.secretKey),
+          }),
+        ])
+        .signers([randomUser])
+        .rpc();
+
+    try {
+      await updateClaimSigner(randomUser, randomUser.publicKey);
+      assert.fail("Expected transaction to fail with Unauthorized");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("Unauthorized");
+    }
+
+    const [paused] = await getCpiEvents(
+      redPacketProgram,
+      await updateClaimSigner(redPacketCreator, null)
+    );
+    expect(paused.name).equal("redPacketClaimSignerUpdated");
+    expect(paused.data.claimsPaused).equal(true);
+    try {
+      await claim(claimer_issuer);
+      assert.fail("Expected transaction to fail with ClaimsPaused");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClaimsPaused");
+    }
+
+    const newIssuer = Keypair.generate();
+    const [rotated] = await getCpiEvents(
+      redPacketProgram,
+      await updateClaimSigner(redPacketCreator, newIssuer.publicKey)
+    );
+    expect(rotated.data.oldSigner.toBase58()).equal(
+      claimer_issuer.publicKey.toBase58()
+    );
+    expect(rotated.data.newSigner.toBase58()).equal(
+      newIssuer.publicKey.toBase58()
+    );
+    expect(rotated.data.claimsPaused).equal(false);
+    // signatures of the old key are void once it is rotated out
+    try {
+      await claim(claimer_issuer);
+      assert.fail("Expected transaction to fail with InvalidSignature");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidSignature");
+    }
+    await provider.connection.confirmTransaction(await claim(newIssuer));
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber).equal(1);
+  });
+
+  it("claim native token red packet approved by 2 of 3 claim signers", async () => {
+    const issuers = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    const id = anchor.web3.Keypair.generate().publicKey.toBytes();
+    const redPacket = PublicKey.findProgramAddressSync(
+      [redPacketCreator.publicKey.toBuffer(), Buffer.from(id)],
+      redPacketProgram.programId
+    )[0];
+    const create = (signers: PublicKey[], threshold: number) =>
+      redPacketProgram.methods
+        .createRedPacketWithId(
+          Array.from(id),
+          { native: {} }, // asset_kind
+          2,
+          new anchor.BN(0.2 * LAMPORTS_PER_SOL),
+          new anchor.BN(60 * 60 * 24),
+          new anchor.BN(0), // open_time
+          { equal: {} }, // split_mode
+          claimer_issuer.publicKey, // unused by multi-signature claims
+          "multi-signature red packet",
+          "multi-signature red packet",
+          false, // use_claim_receipts
+          { multiSignature: { signers, threshold } }, // claim_authorization
+          { timestamp: {} } // randomness
+        )
+        .accounts({
+          signer: redPacketCreator.publicKey,
+          redPacket,
+          tokenMint: null,
+          tokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([redPacketCreator])
+        .rpc();
+
+    const signers = issuers.map((issuer) => issuer.publicKey);
+    for (const [invalidSigners, threshold] of [
+      [signers, 0],
+      [signers, 4],
+      [[signers[0], signers[1], signers[0]], 2],
+    ] as [PublicKey[], number][]) {
+      try {
+        await create(invalidSigners, threshold);
+        assert.fail("Expected transaction to fail with InvalidClaimSigners");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidClaimSigners");
+      }
+    }
+    await provider.connection.confirmTransaction(await create(signers, 2));
+
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+    const claim = (approvers: Keypair[]) =>
+      redPacketProgram.methods
+        .claimRedPacket({ none: {} })
+        .accounts({
+          redPacket,
+          signer: randomUser.publicKey,
+          recipient: randomUser.publicKey,
+          creator: null,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          tokenMint: null,
+          recipientTokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([multiEd25519Instruction(message, approvers)])
+        .signers([randomUser])
+        .rpc();
+
+    // one approval, the same approval twice, or an approval from an unknown key fall short
+    for (const approvers of [
+      [issuers[0]],
+      [issuers[0], issuers[0]],
+      [issuers[0], Keypair.generate()],
+    ]) {
+      try {
+        await claim(approvers);
+        assert.fail("Expected transaction to fail with InvalidSignature");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidSignature");
+      }
+    }
+    await provider.connection.confirmTransaction(
+      await claim([issuers[2], issuers[0]])
+    );
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber).equal(1);
+
+    // the signer set rotates with update_claim_authorization, not update_claim_signer
+    try {
+      await redPacketProgram.methods
+        .updateClaimSigner(issuers[0].publicKey)
+        .accounts({ signer: redPacketCreator.publicKey, redPacket })
+        .signers([redPacketCreator])
+        .rpc();
+      assert.fail("Expected transaction to fail with ClaimSignerNotUsed");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("ClaimSignerNotUsed");
+    }
+    const updateClaimAuthorization = (claimAuthorization) =>
+      redPacketProgram.methods
+        .updateClaimAuthorization(claimAuthorization)
+        .accounts({ signer: redPacketCreator.publicKey, redPacket })
+        .signers([redPacketCreator])
+        .rpc();
+    for (const [claimAuthorization, code] of [
+      [{ signature: {} }, "InvalidClaimAuthorization"],
+      [{ multiSignature: { signers: [signers[1]], threshold: 2 } }, "InvalidClaimSigners"],
+    ] as [any, string][]) {
+      try {
+        await updateClaimAuthorization(claimAuthorization);
+        assert.fail(`Expected transaction to fail with ${code}`);
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal(code);
+      }
+    }
+    const rotated = { multiSignature: { signers: [signers[1], signers[2]], threshold: 2 } };
+    await provider.connection.confirmTransaction(
+      await updateClaimAuthorization(rotated)
+    );
+    const rotatedAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(rotatedAccount.claimAuthorization).to.deep.equal(rotated);
+  });
+
+  it("claim native token red packet with a secp256k1 claim signature", async () => {
+    const ethPrivateKey = nacl.randomBytes(32);
+    const secp256k1Instruction = (message: Buffer) =>
+      Secp256k1Program.createInstructionWithPrivateKey({
+        privateKey: ethPrivateKey,
+        message,
+        instructionIndex: 0,
+      });
+    // web3.js stores the Ethereum address right after the one signature offsets block
+    const ethAddress = secp256k1Instruction(Buffer.alloc(0)).data.subarray(12, 32);
+    const id = anchor.web3.Keypair.generate().publicKey.toBytes();
+    const redPacket = PublicKey.findProgramAddressSync(
+      [redPacketCreator.publicKey.toBuffer(), Buffer.from(id)],
+      redPacketProgram.programId
+    )[0];
+    const createTx = await redPacketProgram.methods
+      .createRedPacketWithId(
+        Array.from(id),
+        { native: {} }, // asset_kind
+        2,
+        new anchor.BN(0.2 * LAMPORTS_PER_SOL),
+        new anchor.BN(60 * 60 * 24),
+        new anchor.BN(0), // open_time
+        { equal: {} }, // split_mode
+        claimer_issuer.publicKey, // unused by secp256k1 claims
+        "secp256k1 red packet",
+        "secp256k1 red packet",
+        false, // use_claim_receipts
+        { secp256k1: { ethAddress: Array.from(ethAddress) } }, // claim_authorization
+        { timestamp: {} } // randomness
+      )
+      .accounts({
+        signer: redPacketCreator.publicKey,
+        redPacket,
+        tokenMint: null,
+        tokenAccount: null,
+        vault: null,
+        tokenProgram: null,
+        associatedTokenProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+    await provider.connection.confirmTransaction(createTx);
+
+    const claim = (
+      verifyInstruction: anchor.web3.TransactionInstruction,
+      claimer = randomUser
+    ) =>
+      redPacketProgram.methods
+        .claimRedPacket({ none: {} })
+        .accounts({
+          redPacket,
+          signer: claimer.publicKey,
+          recipient: claimer.publicKey,
+          creator: null,
+          claimReceipt: null,
+          passwordCommit: null,
+          randomCommit: null,
+          tokenMint: null,
+          recipientTokenAccount: null,
+          vault: null,
+          tokenProgram: null,
+          associatedTokenProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([verifyInstruction])
+        .signers([claimer])
+        .rpc();
+    const message = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser.publicKey.toBytes(),
+      randomUser.publicKey.toBytes(),
+    ]);
+
+    // an Ed25519 signature of the same message does not satisfy a secp256k1 packet
+    try {
+      await claim(
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: claimer_issuer.publicKey.toBytes(),
+          message: message,
+          signature: nacl.sign.detached(message, claimer_issuer.secretKey),
+        })
+      );
+      assert.fail("Expected transaction to fail with InvalidSignature");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidSignature");
+    }
+    // a valid signature over a message for another recipient
+    try {
+      await claim(
+        secp256k1Instruction(
+          Buffer.concat([
+            redPacket.toBytes(),
+            randomUser.publicKey.toBytes(),
+            randomUser2.publicKey.toBytes(),
+          ])
+        )
+      );
+      assert.fail("Expected transaction to fail with InvalidSignature");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InvalidSignature");
+    }
+
+    await provider.connection.confirmTransaction(
+      await claim(secp256k1Instruction(message))
+    );
+
+    // wallets sign with personal_sign, which hashes the message behind the EIP-191 prefix
+    const message2 = Buffer.concat([
+      redPacket.toBytes(),
+      randomUser2.publicKey.toBytes(),
+      randomUser2.publicKey.toBytes(),
+    ]);
+    const personalSignMessage = Buffer.concat([
+      Buffer.from(`\x19Ethereum Signed Message:\n${message2.length}`),
+      message2,
+    ]);
+    await provider.connection.confirmTransaction(
+      await claim(secp256k1Instruction(personalSignMessage), randomUser2)
+    );
+    const redPacketAccount = await redPacketProgram.account.redPacket.fetch(
+      redPacket
+    );
+    expect(redPacketAccount.claimedNumber).equal(2);
+  });
+
+  it("withdraw spl token red packet", async () => {
+    // Re-derive the PDA
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(splRedPacketCreateTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+
+    // tokens sent to the vault by anyone go back to the creator with the remainder
+    await transfer(
+      connection,
+      redPacketCreator,
+      tokenAccount,
+      vault,
+      redPacketCreator,
+      1000,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const vaultBalance = new anchor.BN(
+      (await connection.getTokenAccountBalance(vault)).value.amount
+    );
+    const creatorBalanceBefore = new anchor.BN(
+      (await connection.getTokenAccountBalance(tokenAccount)).value.amount
+    );
+
+    // Now perform withdrawal
+    const withdrawTx = await redPacketProgram.methods
+      .withdrawWithSplToken()
+      .accounts({
+        redPacket,
+        signer: redPacketCreator.publicKey,
+        vault: vault,
+        tokenAccount: tokenAccount, // Will be created if it doesn't exist
+        tokenMint: tokenMint,
+        tokenProgram: TOKEN_PROGRAM,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+
+    await provider.connection.confirmTransaction(withdrawTx);
+
+    const creatorBalanceAfter = (
+      await connection.getTokenAccountBalance(tokenAccount)
+    ).value.amount;
+    expect(creatorBalanceAfter).equal(
+      creatorBalanceBefore.add(vaultBalance).toString()
+    );
+    expect(await connection.getAccountInfo(vault)).to.be.null;
+
+    // Verify the account is closed
+    try {
+      await redPacketProgram.account.redPacket.fetch(redPacket);
+      assert.fail("Expected account to be closed");
+    } catch (error) {
+      expect(error.message).to.include("Account does not exist");
+    }
+  });
+
+  it("withdraw native token red packet", async () => {
+    // Re-derive the PDA
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(nativeRedPacketCreateTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+    // Now perform withdrawal
+    const withdrawTx = await redPacketProgram.methods
+      .withdrawWithNativeToken()
+      .accounts({
+        redPacket,
+        signer: redPacketCreator.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([redPacketCreator])
+      .rpc();
+
+    await provider.connection.confirmTransaction(withdrawTx);
+    await getLogs(withdrawTx);
+
+    // Verify the account is closed
+    try {
+      await redPacketProgram.account.redPacket.fetch(redPacket);
+      assert.fail("Expected account to be closed");
+    } catch (error) {
+      expect(error.message).to.include("Account does not exist");
+    }
+  });
+
+  it("fail to create spl red packet with invalid parameters", async () => {
+    // Re-derive the PDA
+    const createTime = new anchor.BN(Math.floor(Date.now() / 1000) - 100);
+    const redPacket = PublicKey.findProgramAddressSync(
+      [
+        redPacketCreator.publicKey.toBuffer(),
+        Buffer.from(createTime.toArray("le", 8)),
+      ],
+      redPacketProgram.programId
+    )[0];
+
+    // Get vault account
+    const vaultAccount = getAssociatedTokenAddressSync(
+      tokenMint,
+      redPacket,
+      true,
+      TOKEN_PROGRAM
+    );
+    const duration = new anchor.BN(200);
+    const totalNumber = 1;
+    const totalAmount = new anchor.BN(1 * LAMPORTS_PER_SOL);
 
     try {
       const tx = await redPacketProgram.methods
@@ -1009,10 +3287,14 @@ describe("redpacket", () => {
           totalAmount,
           createTime,
           duration.sub(new anchor.BN(110)),
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create spl red packet"
+          "will fail create spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1042,10 +3324,14 @@ describe("redpacket", () => {
           totalAmount,
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create spl red packet"
+          "will fail create spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1070,14 +3356,18 @@ describe("redpacket", () => {
     try {
       const tx = await redPacketProgram.methods
         .createRedPacketWithSplToken(
-          201,
+          101,
           totalAmount,
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create spl red packet"
+          "will fail create spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1106,10 +3396,14 @@ describe("redpacket", () => {
           new anchor.BN(0),
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create spl red packet"
+          "will fail create spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1138,10 +3432,14 @@ describe("redpacket", () => {
           new anchor.BN(1000 * LAMPORTS_PER_SOL),
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create spl red packet"
+          "will fail create spl red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1186,10 +3484,14 @@ describe("redpacket", () => {
           totalAmount,
           createTime,
           duration.sub(new anchor.BN(110)),
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create native red packet"
+          "will fail create native red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1214,10 +3516,14 @@ describe("redpacket", () => {
           totalAmount,
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create native red packet"
+          "will fail create native red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1237,14 +3543,18 @@ describe("redpacket", () => {
     try {
       const tx = await redPacketProgram.methods
         .createRedPacketWithNativeToken(
-          201,
+          101,
           totalAmount,
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create native red packet"
+          "will fail create native red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1268,10 +3578,14 @@ describe("redpacket", () => {
           new anchor.BN(0),
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create native red packet"
+          "will fail create native red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1295,10 +3609,14 @@ describe("redpacket", () => {
           new anchor.BN(1000 * LAMPORTS_PER_SOL),
           createTime,
           duration,
-          false,
+          createTime, // open_time
+          { equal: {} }, // split_mode
           claimer_issuer.publicKey,
           "will fail create red packet",
-          "will fail create native red packet"
+          "will fail create native red packet",
+          false, // use_claim_receipts
+          { signature: {} }, // claim_authorization
+          { timestamp: {} } // randomness
         )
         .accounts({
           signer: redPacketCreator.publicKey,
@@ -1317,6 +3635,107 @@ describe("redpacket", () => {
   });
 });
 
+// Mirrors programs/solana-redpacket/src/merkle.rs
+function allowlistLeaf(claimer: PublicKey, amount: anchor.BN): Buffer {
+  return createHash("sha256")
+    .update(Buffer.from([0]))
+    .update(claimer.toBuffer())
+    .update(Buffer.from(amount.toArray("le", 8)))
+    .digest();
+}
+
+function hashPair(a: Buffer, b: Buffer): Buffer {
+  const [left, right] = Buffer.compare(a, b) <= 0 ? [a, b] : [b, a];
+  return createHash("sha256")
+    .update(Buffer.from([1]))
+    .update(left)
+    .update(right)
+    .digest();
+}
+
+// Each layer pairs neighbours; an odd node out is promoted unchanged
+function merkleLayers(leaves: Buffer[]): Buffer[][] {
+  const layers = [leaves];
+  while (layers[layers.length - 1].length > 1) {
+    const layer = layers[layers.length - 1];
+    const next: Buffer[] = [];
+    for (let i = 0; i < layer.length; i += 2) {
+      next.push(i + 1 < layer.length ? hashPair(layer[i], layer[i + 1]) : layer[i]);
+    }
+    layers.push(next);
+  }
+  return layers;
+}
+
+function merkleRoot(leaves: Buffer[]): Buffer {
+  const layers = merkleLayers(leaves);
+  return layers[layers.length - 1][0];
+}
+
+function merkleProof(leaves: Buffer[], index: number): Buffer[] {
+  const proof: Buffer[] = [];
+  for (const layer of merkleLayers(leaves).slice(0, -1)) {
+    const sibling = index ^ 1;
+    if (sibling < layer.length) {
+      proof.push(layer[sibling]);
+    }
+    index = Math.floor(index / 2);
+  }
+  return proof;
+}
+
+// Mirrors ed25519_verify_instruction_multi in client/src/signature.rs: every signature covers the same message
+function multiEd25519Instruction(message: Buffer, signers: Keypair[]) {
+  const keysOffset = 2 + signers.length * 14;
+  const messageOffset = keysOffset + signers.length * (32 + 64);
+  const data = Buffer.alloc(messageOffset + message.length);
+  data.writeUInt8(signers.length, 0);
+  signers.forEach((signer, index) => {
+    const publicKeyOffset = keysOffset + index * (32 + 64);
+    const offsets = 2 + index * 14;
+    data.writeUInt16LE(publicKeyOffset + 32, offsets); // signature_offset
+    data.writeUInt16LE(0xffff, offsets + 2); // signature_instruction_index
+    data.writeUInt16LE(publicKeyOffset, offsets + 4); // public_key_offset
+    data.writeUInt16LE(0xffff, offsets + 6); // public_key_instruction_index
+    data.writeUInt16LE(messageOffset, offsets + 8); // message_data_offset
+    data.writeUInt16LE(message.length, offsets + 10); // message_data_size
+    data.writeUInt16LE(0xffff, offsets + 12); // message_instruction_index
+    signer.publicKey.toBuffer().copy(data, publicKeyOffset);
+    Buffer.from(nacl.sign.detached(message, signer.secretKey)).copy(
+      data,
+      publicKeyOffset + 32
+    );
+  });
+  message.copy(data, messageOffset);
+  return new anchor.web3.TransactionInstruction({
+    programId: Ed25519Program.programId,
+    keys: [],
+    data,
+  });
+}
+
+// Events emitted with emit_cpi! are self-invocations of the program, decoded from the inner instructions
+async function getCpiEvents(program: Program<Redpacket>, signature: string) {
+  const tx = await program.provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const programIndex = tx.transaction.message
+    .getAccountKeys()
+    .staticAccountKeys.findIndex((key) => key.equals(program.programId));
+  return tx.meta.innerInstructions
+    .flatMap((inner) => inner.instructions)
+    .filter((ix) => ix.programIdIndex === programIndex)
+    .map((ix) => {
+      const data = bs58.decode(ix.data);
+      // skip the 8-byte event CPI instruction tag
+      return program.coder.events.decode(
+        anchor.utils.bytes.base64.encode(Buffer.from(data.slice(8)))
+      );
+    })
+    .filter((event) => event !== null);
+}
+
 async function getLogs(signature: string) {
   try {
     const provider = anchor.AnchorProvider.env();